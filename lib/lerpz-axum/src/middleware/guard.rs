@@ -0,0 +1,461 @@
+//! Layers that only let requests through if their token grants a scope or
+//! role.
+//!
+//! The guards work with any token extractor implementing [`Permissions`],
+//! which includes both [`AzureAccessToken`](super::azure::AzureAccessToken)
+//! and [`AccessToken`](super::jwt::AccessToken).
+//!
+//! Requests without a valid token are rejected by the extractor, which is a
+//! `401 Unauthorized`. Requests with a valid token that lacks the scope or role
+//! are rejected with [`HandlerError::forbidden()`].
+//!
+//! ### Example
+//!
+//! ```rust
+//! # use axum::{Router, extract::FromRequestParts, http::request::Parts, routing::get};
+//! # use lerpz_axum::{error::HandlerError, middleware::guard::{Permissions, RequireScope}};
+//! # struct AccessToken;
+//! # impl<S: Send + Sync> FromRequestParts<S> for AccessToken {
+//! #     type Rejection = HandlerError;
+//! #     async fn from_request_parts(_: &mut Parts, _: &S) -> Result<Self, HandlerError> {
+//! #         Ok(AccessToken)
+//! #     }
+//! # }
+//! # impl Permissions for AccessToken {
+//! #     fn has_scope(&self, _: &str) -> bool { true }
+//! #     fn has_role(&self, _: &str) -> bool { true }
+//! # }
+//! # #[derive(Clone)]
+//! # struct AppState;
+//! # async fn handler() {}
+//! fn router(state: AppState) -> Router<AppState> {
+//!     Router::new()
+//!         .route("/", get(handler))
+//!         .route_layer(RequireScope::<AccessToken, _>::new(state.clone(), "dept.read"))
+//!         .with_state(state)
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service};
+
+use crate::error::HandlerError;
+
+/// How many parents are followed before giving up.
+///
+/// This stops a cycle in the hierarchy from looping forever.
+const MAX_DEPTH: usize = 16;
+
+/// A token that grants scopes and roles.
+pub trait Permissions {
+    /// Whether the token was granted exactly this scope.
+    fn has_scope(&self, scope: &str) -> bool;
+
+    /// Whether the token was granted the role.
+    fn has_role(&self, role: &str) -> bool;
+}
+
+/// Which scopes are children of which.
+///
+/// A token granted a parent scope is also granted all of its children. This
+/// is cheap to clone, since clones share the same hierarchy.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeHierarchy {
+    parents: Arc<HashMap<String, String>>,
+}
+
+/// What a token must grant to pass a guard.
+#[derive(Debug, Clone)]
+enum Requirement {
+    Scope(String),
+    Role(String),
+}
+
+/// The shared parts of every guard.
+struct Guard<T, S> {
+    state: S,
+    hierarchy: ScopeHierarchy,
+    requirements: Arc<[Requirement]>,
+    _token: PhantomData<fn() -> T>,
+}
+
+/// Layer that requires the token to grant a scope.
+///
+/// The scope is also granted by any of its parents in the [`ScopeHierarchy`].
+pub struct RequireScope<T, S>(Guard<T, S>);
+
+/// Layer that requires the token to grant a role.
+pub struct RequireRole<T, S>(Guard<T, S>);
+
+/// Layer that requires the token to grant at least one of the scopes or roles.
+pub struct RequireAny<T, S>(Guard<T, S>);
+
+/// Service created by the guard layers.
+pub struct GuardService<T, S, I> {
+    guard: Guard<T, S>,
+    inner: I,
+}
+
+impl ScopeHierarchy {
+    /// Create a new empty [`ScopeHierarchy`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a [`ScopeHierarchy`] from `(child, parent)` pairs.
+    pub fn from_pairs<I, C, P>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (C, P)>,
+        C: Into<String>,
+        P: Into<String>,
+    {
+        let parents = pairs
+            .into_iter()
+            .map(|(child, parent)| (child.into(), parent.into()))
+            .collect();
+        Self {
+            parents: Arc::new(parents),
+        }
+    }
+
+    /// Whether the token grants the scope, either directly or through one of
+    /// its parents.
+    pub fn grants(&self, token: &impl Permissions, scope: &str) -> bool {
+        if token.has_scope(scope) {
+            return true;
+        }
+
+        let mut current = scope;
+        for _ in 0..MAX_DEPTH {
+            let Some(parent) = self.parents.get(current) else {
+                return false;
+            };
+            if token.has_scope(parent) {
+                return true;
+            }
+            current = parent;
+        }
+        false
+    }
+}
+
+impl<T, S> Guard<T, S> {
+    fn new(state: S, requirements: Vec<Requirement>) -> Self {
+        Self {
+            state,
+            hierarchy: ScopeHierarchy::new(),
+            requirements: requirements.into(),
+            _token: PhantomData,
+        }
+    }
+
+    /// Whether the token meets at least one of the requirements.
+    fn allows(&self, token: &impl Permissions) -> bool {
+        self.requirements
+            .iter()
+            .any(|requirement| match requirement {
+                Requirement::Scope(scope) => self.hierarchy.grants(token, scope),
+                Requirement::Role(role) => token.has_role(role),
+            })
+    }
+}
+
+impl<T, S: Clone> Clone for Guard<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            hierarchy: self.hierarchy.clone(),
+            requirements: self.requirements.clone(),
+            _token: PhantomData,
+        }
+    }
+}
+
+impl<T, S> RequireScope<T, S> {
+    /// Create a new [`RequireScope`] layer.
+    ///
+    /// The state is used to extract the token `T`.
+    pub fn new(state: S, scope: impl Into<String>) -> Self {
+        Self(Guard::new(state, vec![Requirement::Scope(scope.into())]))
+    }
+
+    /// Use the hierarchy to find the parents of the scope.
+    pub fn with_hierarchy(mut self, hierarchy: ScopeHierarchy) -> Self {
+        self.0.hierarchy = hierarchy;
+        self
+    }
+}
+
+impl<T, S> RequireRole<T, S> {
+    /// Create a new [`RequireRole`] layer.
+    ///
+    /// The state is used to extract the token `T`.
+    pub fn new(state: S, role: impl Into<String>) -> Self {
+        Self(Guard::new(state, vec![Requirement::Role(role.into())]))
+    }
+}
+
+impl<T, S> RequireAny<T, S> {
+    /// Create a new [`RequireAny`] layer without any scopes or roles.
+    ///
+    /// The state is used to extract the token `T`. A guard without any scopes
+    /// or roles rejects every request.
+    pub fn new(state: S) -> Self {
+        Self(Guard::new(state, Vec::new()))
+    }
+
+    /// Let requests with the scope through.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.push(Requirement::Scope(scope.into()));
+        self
+    }
+
+    /// Let requests with the role through.
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.push(Requirement::Role(role.into()));
+        self
+    }
+
+    /// Use the hierarchy to find the parents of the scopes.
+    pub fn with_hierarchy(mut self, hierarchy: ScopeHierarchy) -> Self {
+        self.0.hierarchy = hierarchy;
+        self
+    }
+
+    fn push(&mut self, requirement: Requirement) {
+        let mut requirements = self.0.requirements.to_vec();
+        requirements.push(requirement);
+        self.0.requirements = requirements.into();
+    }
+}
+
+/// Implements [`Clone`] and [`Layer`] for the guard layers.
+macro_rules! impl_guard_layer {
+    ($($layer:ident),*) => {
+        $(
+            impl<T, S: Clone> Clone for $layer<T, S> {
+                fn clone(&self) -> Self {
+                    Self(self.0.clone())
+                }
+            }
+
+            impl<T, S: Clone, I> Layer<I> for $layer<T, S> {
+                type Service = GuardService<T, S, I>;
+
+                fn layer(&self, inner: I) -> Self::Service {
+                    GuardService {
+                        guard: self.0.clone(),
+                        inner,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_guard_layer!(RequireScope, RequireRole, RequireAny);
+
+impl<T, S: Clone, I: Clone> Clone for GuardService<T, S, I> {
+    fn clone(&self) -> Self {
+        Self {
+            guard: self.guard.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, S, I> Service<Request> for GuardService<T, S, I>
+where
+    T: FromRequestParts<S> + Permissions + Send + 'static,
+    T::Rejection: IntoResponse,
+    S: Clone + Send + Sync + 'static,
+    I: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    I::Future: Send,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let guard = self.guard.clone();
+        // The clone might not be ready, so the service that was polled is used
+        // and the clone is left in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let (mut parts, body) = req.into_parts();
+            let token = match T::from_request_parts(&mut parts, &guard.state).await {
+                Ok(token) => token,
+                Err(rejection) => return Ok(rejection.into_response()),
+            };
+
+            if !guard.allows(&token) {
+                return Ok(HandlerError::<()>::forbidden().into_response());
+            }
+
+            inner.call(Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+#[cfg(feature = "azure")]
+impl Permissions for super::azure::AzureAccessToken {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.has_scope(scope)
+    }
+
+    fn has_role(&self, role: &str) -> bool {
+        self.has_role(role)
+    }
+}
+
+#[cfg(feature = "jwt")]
+impl<C: lerpz_jwt::HasClaims> Permissions for super::jwt::AccessToken<C> {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.has_scope(scope)
+    }
+
+    fn has_role(&self, role: &str) -> bool {
+        self.has_role(role)
+    }
+}
+
+#[cfg(feature = "jwt")]
+impl<T: Permissions> Permissions for super::jwt::Unrevoked<T> {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.0.has_scope(scope)
+    }
+
+    fn has_role(&self, role: &str) -> bool {
+        self.0.has_role(role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{StatusCode, request::Parts},
+        routing::get,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    struct Token {
+        scopes: HashSet<&'static str>,
+        roles: HashSet<&'static str>,
+    }
+
+    impl Permissions for Token {
+        fn has_scope(&self, scope: &str) -> bool {
+            self.scopes.contains(scope)
+        }
+
+        fn has_role(&self, role: &str) -> bool {
+            self.roles.contains(role)
+        }
+    }
+
+    #[test]
+    fn test_scope_hierarchy() {
+        let hierarchy = ScopeHierarchy::from_pairs([
+            ("dept.read", "dept"),
+            ("dept.write", "dept"),
+            ("dept", "admin"),
+            ("loop.a", "loop.b"),
+            ("loop.b", "loop.a"),
+        ]);
+        let token = Token {
+            scopes: HashSet::from(["dept"]),
+            roles: HashSet::from(["manager"]),
+        };
+
+        assert!(hierarchy.grants(&token, "dept.read"));
+        assert!(hierarchy.grants(&token, "dept"));
+        assert!(!hierarchy.grants(&token, "admin"));
+        assert!(!hierarchy.grants(&token, "loop.a"));
+        assert!(!ScopeHierarchy::new().grants(&token, "dept.read"));
+
+        let guard = RequireAny::<Token, ()>::new(())
+            .scope("admin")
+            .role("manager")
+            .with_hierarchy(hierarchy);
+        assert!(guard.0.allows(&token));
+        assert!(!RequireAny::<Token, ()>::new(()).0.allows(&token));
+    }
+
+    /// A token read from the space separated scopes in the `scopes` header.
+    struct HeaderToken(Vec<String>);
+
+    impl<S: Send + Sync> FromRequestParts<S> for HeaderToken {
+        type Rejection = HandlerError;
+
+        async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+            let scopes = parts
+                .headers
+                .get("scopes")
+                .and_then(|scopes| scopes.to_str().ok())
+                .ok_or_else(HandlerError::unauthorized)?;
+            Ok(Self(scopes.split_whitespace().map(String::from).collect()))
+        }
+    }
+
+    impl Permissions for HeaderToken {
+        fn has_scope(&self, scope: &str) -> bool {
+            self.0.iter().any(|s| s == scope)
+        }
+
+        fn has_role(&self, _: &str) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guard_service() {
+        let guard = RequireScope::<HeaderToken, _>::new((), "dept.read")
+            .with_hierarchy(ScopeHierarchy::from_pairs([("dept.read", "dept")]));
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route_layer(guard);
+
+        let status = |scopes: Option<&str>| {
+            let app = app.clone();
+            let mut request = Request::builder().uri("/");
+            if let Some(scopes) = scopes {
+                request = request.header("scopes", scopes);
+            }
+            async move {
+                let request = request.body(Body::empty()).unwrap();
+                app.oneshot(request).await.unwrap().status()
+            }
+        };
+
+        assert_eq!(status(Some("dept.read")).await, StatusCode::OK);
+        assert_eq!(status(Some("profile dept")).await, StatusCode::OK);
+        assert_eq!(status(Some("dept.write")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(Some("")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::BTreeSet, ops::Deref};
+
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use lerpz_jwt::{Claims, HasClaims, UserClaims};
+use serde::de::DeserializeOwned;
+
+use super::JwtConfig;
+use crate::error::HandlerError;
+
+/// A token minted by `lerpz-jwt`.
+///
+/// This can be extracted in any handler by adding it as a parameter. The
+/// claims default to [`UserClaims`], but any type implementing [`HasClaims`]
+/// can be used.
+///
+/// ### Example
+///
+/// ```rust
+/// # use lerpz_axum::{error::HandlerResult, middleware::jwt::AccessToken};
+/// async fn example_handler(
+///     token: AccessToken,
+/// ) -> HandlerResult<String> {
+///     token.has_scope_or_unauthorized("example/scope")?;
+///
+///     Ok(format!("Hello {}!", token.username))
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AccessToken<C = UserClaims>(pub C);
+
+impl<C: HasClaims> AccessToken<C> {
+    /// Check if the token has scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.0.claims().has_scope(scope)
+    }
+
+    /// Check if the token has any of scopes.
+    pub fn has_any_scope(&self, scopes: &[&str]) -> bool {
+        scopes.iter().any(|scope| self.has_scope(scope))
+    }
+
+    /// Check if the token has scope.
+    ///
+    /// This will return [`HandlerError::unauthorized()`] if scope is not found.
+    #[allow(clippy::result_large_err)]
+    pub fn has_scope_or_unauthorized(&self, scope: &str) -> Result<(), HandlerError> {
+        self.has_scope(scope)
+            .then_some(())
+            .ok_or(HandlerError::unauthorized())
+    }
+
+    /// Check if the token has any of scopes.
+    ///
+    /// This will return [`HandlerError::unauthorized()`] if all scopes are not found.
+    #[allow(clippy::result_large_err)]
+    pub fn has_any_scope_or_unauthorized(&self, scopes: &[&str]) -> Result<(), HandlerError> {
+        self.has_any_scope(scopes)
+            .then_some(())
+            .ok_or(HandlerError::unauthorized())
+    }
+
+    /// Check if the token has role.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.0.roles().is_some_and(|roles| roles.contains(role))
+    }
+
+    /// Check if the token has any of roles.
+    pub fn has_any_role(&self, roles: &[&str]) -> bool {
+        roles.iter().any(|role| self.has_role(role))
+    }
+
+    /// Check if the token has role.
+    ///
+    /// This will return [`HandlerError::unauthorized()`] if role is not found.
+    #[allow(clippy::result_large_err)]
+    pub fn has_role_or_unauthorized(&self, role: &str) -> Result<(), HandlerError> {
+        self.has_role(role)
+            .then_some(())
+            .ok_or(HandlerError::unauthorized())
+    }
+
+    /// Check if the token has any of roles.
+    ///
+    /// This will return [`HandlerError::unauthorized()`] if all roles are not found.
+    #[allow(clippy::result_large_err)]
+    pub fn has_any_role_or_unauthorized(&self, roles: &[&str]) -> Result<(), HandlerError> {
+        self.has_any_role(roles)
+            .then_some(())
+            .ok_or(HandlerError::unauthorized())
+    }
+
+    /// Returns the claims of the token.
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C> Deref for AccessToken<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<C: HasClaims> HasClaims for AccessToken<C> {
+    fn claims(&self) -> &Claims {
+        self.0.claims()
+    }
+
+    fn roles(&self) -> Option<&BTreeSet<String>> {
+        self.0.roles()
+    }
+}
+
+impl<S, C> FromRequestParts<S> for AccessToken<C>
+where
+    C: DeserializeOwned + HasClaims,
+    JwtConfig: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = HandlerError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or_else(HandlerError::unauthorized)?;
+
+        let config = JwtConfig::from_ref(state);
+        let token_data = config
+            .keys()
+            .decode::<C>(token, config.verifier())
+            .map_err(HandlerError::unauthorized_with_error)?;
+
+        Ok(AccessToken(token_data.claims))
+    }
+}
@@ -0,0 +1,29 @@
+use lerpz_jwt::{KeyRing, VerifierConfig};
+
+/// Everything needed to verify tokens minted by `lerpz-jwt`.
+///
+/// This is resolved from the state of the router using
+/// [`FromRef`](axum::extract::FromRef), like the
+/// [`AzureConfig`](crate::middleware::azure::AzureConfig) is.
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    keys: KeyRing,
+    verifier: VerifierConfig,
+}
+
+impl JwtConfig {
+    /// Create a new [`JwtConfig`].
+    pub fn new(keys: KeyRing, verifier: VerifierConfig) -> Self {
+        Self { keys, verifier }
+    }
+
+    /// The keys tokens are verified with.
+    pub fn keys(&self) -> &KeyRing {
+        &self.keys
+    }
+
+    /// The rules tokens are verified against.
+    pub fn verifier(&self) -> &VerifierConfig {
+        &self.verifier
+    }
+}
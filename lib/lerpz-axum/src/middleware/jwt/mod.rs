@@ -0,0 +1,7 @@
+pub use access_token::*;
+pub use config::*;
+pub use revocation::*;
+
+mod access_token;
+mod config;
+mod revocation;
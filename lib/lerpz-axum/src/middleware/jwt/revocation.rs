@@ -0,0 +1,310 @@
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use bb8_redis::RedisConnectionManager;
+use chrono::{DateTime, Utc};
+use lerpz_jwt::HasClaims;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::HandlerError;
+
+/// Prefix of the Redis keys that mark whether a `jti` is revoked.
+const REDIS_PREFIX: &str = "revoked:jti:";
+
+/// How many seconds Redis remembers that a token isn't revoked.
+///
+/// This is how long a token can still be used after it was revoked, if the
+/// revocation couldn't be written to Redis.
+const NOT_REVOKED_TTL: i64 = 60;
+
+/// A type alias for [`Result<T, RevocationError>`].
+pub type RevocationResult<T> = std::result::Result<T, RevocationError>;
+
+/// Errors that can occur when reading or writing the revocation list.
+#[derive(thiserror::Error, Debug)]
+pub enum RevocationError {
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+    #[error(transparent)]
+    RedisPool(#[from] bb8::RunError<redis::RedisError>),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// The list of access tokens that have been revoked before they expired.
+///
+/// The `access_tokens` table is the source of truth. Every revoked `jti` is
+/// also written to Redis with a TTL equal to the remaining lifetime of the
+/// token, so checking a token is usually a single Redis lookup. Postgres is
+/// consulted when Redis doesn't know the token or can't be reached, and the
+/// outcome is written back to Redis. That a token isn't revoked is only
+/// remembered for a short while.
+#[derive(Clone)]
+pub struct RevocationList {
+    redis: bb8::Pool<RedisConnectionManager>,
+    database: PgPool,
+}
+
+impl RevocationList {
+    /// Create a new [`RevocationList`].
+    pub fn new(redis: bb8::Pool<RedisConnectionManager>, database: PgPool) -> Self {
+        Self { redis, database }
+    }
+
+    /// Revokes a single token until it expires.
+    pub async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> RevocationResult<()> {
+        sqlx::query(
+            "UPDATE access_tokens SET revoked_at = now() WHERE jti = $1 AND revoked_at IS NULL",
+        )
+        .bind(jti)
+        .execute(&self.database)
+        .await?;
+
+        self.mark_revoked(jti, expires_at).await;
+        Ok(())
+    }
+
+    /// Revokes every token of a user that hasn't expired yet.
+    ///
+    /// Returns the amount of tokens that were revoked.
+    pub async fn revoke_user(&self, user_id: Uuid) -> RevocationResult<usize> {
+        let revoked: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+            "UPDATE access_tokens SET revoked_at = now() \
+             WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > now() \
+             RETURNING jti, expires_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.database)
+        .await?;
+
+        for (jti, expires_at) in &revoked {
+            self.mark_revoked(jti, *expires_at).await;
+        }
+        Ok(revoked.len())
+    }
+
+    /// Marks a token as revoked in Redis until it expires.
+    ///
+    /// For tokens that were revoked in the `access_tokens` table by the caller.
+    /// This always overwrites what Redis remembers about the token. If that
+    /// fails, the key is deleted instead, so that an outdated "not revoked"
+    /// can't outlive the revocation. Failures are only logged, since the token
+    /// is revoked either way.
+    pub async fn mark_revoked(&self, jti: &str, expires_at: DateTime<Utc>) {
+        let Err(err) = self.cache(jti, true, expires_at).await else {
+            return;
+        };
+        tracing::warn!("can't write revocation of {jti} to redis, forgetting it: {err}");
+
+        if let Err(err) = self.forget(jti).await {
+            tracing::warn!("can't forget {jti} in redis: {err}");
+        }
+    }
+
+    /// Whether the token has been revoked.
+    ///
+    /// Falls back to Postgres if Redis doesn't know the token or can't be
+    /// reached.
+    pub async fn is_revoked(&self, jti: &str) -> RevocationResult<bool> {
+        let cached = match self.cached(jti).await {
+            Ok(cached) => cached,
+            Err(err) => {
+                tracing::warn!("can't check revocation in redis, using database: {err}");
+                return Ok(self.stored(jti).await?.is_some_and(|(revoked, _)| revoked));
+            }
+        };
+        if let Some(revoked) = cached {
+            return Ok(revoked);
+        }
+
+        match self.stored(jti).await? {
+            Some((revoked, expires_at)) => {
+                self.try_cache(jti, revoked, expires_at).await;
+                Ok(revoked)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Writes every revoked token that hasn't expired yet to Redis.
+    ///
+    /// This should be run when the service starts, so that revocations made
+    /// while Redis was unavailable or emptied aren't missed.
+    pub async fn restore(&self) -> RevocationResult<usize> {
+        let revoked: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT jti, expires_at FROM access_tokens \
+             WHERE revoked_at IS NOT NULL AND expires_at > now()",
+        )
+        .fetch_all(&self.database)
+        .await?;
+
+        for (jti, expires_at) in &revoked {
+            self.cache(jti, true, *expires_at).await?;
+        }
+        Ok(revoked.len())
+    }
+
+    /// Whether the token is revoked according to Postgres, along with when it
+    /// expires.
+    async fn stored(&self, jti: &str) -> RevocationResult<Option<(bool, DateTime<Utc>)>> {
+        let stored = sqlx::query_as(
+            "SELECT revoked_at IS NOT NULL, expires_at FROM access_tokens WHERE jti = $1",
+        )
+        .bind(jti)
+        .fetch_optional(&self.database)
+        .await?;
+        Ok(stored)
+    }
+
+    /// Writes whether the token is revoked to Redis.
+    ///
+    /// Revoked tokens are remembered until they expire, and tokens that
+    /// aren't revoked for at most [`NOT_REVOKED_TTL`] seconds. That a token
+    /// isn't revoked is only written if Redis doesn't know the token yet, so
+    /// a stale read of Postgres can't overwrite a revocation made meanwhile.
+    async fn cache(
+        &self,
+        jti: &str,
+        revoked: bool,
+        expires_at: DateTime<Utc>,
+    ) -> RevocationResult<()> {
+        let mut ttl = (expires_at - Utc::now()).num_seconds();
+        if !revoked {
+            ttl = ttl.min(NOT_REVOKED_TTL);
+        }
+        if ttl <= 0 {
+            return Ok(());
+        }
+
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(format!("{REDIS_PREFIX}{jti}"))
+            .arg(revoked)
+            .arg("EX")
+            .arg(ttl);
+        if !revoked {
+            cmd.arg("NX");
+        }
+
+        let mut conn = self.redis.get().await?;
+        cmd.query_async::<()>(&mut *conn).await?;
+        Ok(())
+    }
+
+    /// Removes what Redis remembers about the token.
+    async fn forget(&self, jti: &str) -> RevocationResult<()> {
+        let mut conn = self.redis.get().await?;
+        redis::cmd("DEL")
+            .arg(format!("{REDIS_PREFIX}{jti}"))
+            .query_async::<()>(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Like [`Self::cache`], but only logs failures.
+    ///
+    /// Used after reading Postgres, since the outcome is known even if Redis
+    /// can't be written to.
+    async fn try_cache(&self, jti: &str, revoked: bool, expires_at: DateTime<Utc>) {
+        if let Err(err) = self.cache(jti, revoked, expires_at).await {
+            tracing::warn!("can't write revocation of {jti} to redis: {err}");
+        }
+    }
+
+    /// Whether the token is revoked according to Redis, if Redis knows it.
+    async fn cached(&self, jti: &str) -> RevocationResult<Option<bool>> {
+        let mut conn = self.redis.get().await?;
+        let cached = redis::cmd("GET")
+            .arg(format!("{REDIS_PREFIX}{jti}"))
+            .query_async::<Option<bool>>(&mut *conn)
+            .await?;
+        Ok(cached)
+    }
+}
+
+/// Extractor that rejects tokens that have been revoked.
+///
+/// The inner extractor decodes and verifies the token, after which its `jti`
+/// is looked up in the [`RevocationList`]. Tokens without a `jti` can't be
+/// revoked, so they are rejected too.
+///
+/// ### Example
+///
+/// ```rust
+/// # use lerpz_axum::{error::HandlerResult, middleware::jwt::{AccessToken, Unrevoked}};
+/// async fn example_handler(
+///     Unrevoked(token): Unrevoked<AccessToken>,
+/// ) -> HandlerResult<String> {
+///     Ok(format!("Hello {}!", token.username))
+/// }
+/// ```
+pub struct Unrevoked<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for Unrevoked<T>
+where
+    T: FromRequestParts<S> + HasClaims + Send,
+    T::Rejection: Into<HandlerError>,
+    RevocationList: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = HandlerError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let token = T::from_request_parts(parts, state)
+            .await
+            .map_err(Into::into)?;
+
+        let jti = &token.claims().jti;
+        if jti.is_empty() {
+            return Err(HandlerError::unauthorized());
+        }
+
+        let revocations = RevocationList::from_ref(state);
+        if revocations.is_revoked(jti).await? {
+            return Err(HandlerError::unauthorized());
+        }
+
+        Ok(Unrevoked(token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    /// Connects to the Redis server at `REDIS_URL`. Postgres isn't used by
+    /// the tests, so it's never connected to.
+    async fn revocation_list() -> RevocationList {
+        let url = std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1:6379".into());
+        let redis = bb8::Pool::builder()
+            .build(RedisConnectionManager::new(url).unwrap())
+            .await
+            .unwrap();
+        let database = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        RevocationList::new(redis, database)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_stale_read_keeps_revocation() {
+        let revocations = revocation_list().await;
+        let jti = Uuid::new_v4().to_string();
+        let expires_at = Utc::now() + Duration::minutes(5);
+
+        // A request read "not revoked" from Postgres, then the token was
+        // revoked before the request wrote what it read to Redis.
+        revocations.mark_revoked(&jti, expires_at).await;
+        revocations.cache(&jti, false, expires_at).await.unwrap();
+        assert_eq!(revocations.cached(&jti).await.unwrap(), Some(true));
+
+        // The token was known not to be revoked, and then got revoked.
+        let jti = Uuid::new_v4().to_string();
+        revocations.cache(&jti, false, expires_at).await.unwrap();
+        assert_eq!(revocations.cached(&jti).await.unwrap(), Some(false));
+        revocations.mark_revoked(&jti, expires_at).await;
+        assert_eq!(revocations.cached(&jti).await.unwrap(), Some(true));
+    }
+}
@@ -1,4 +1,6 @@
 #[cfg(feature = "azure")]
 pub mod azure;
+pub mod guard;
+#[cfg(feature = "jwt")]
+pub mod jwt;
 pub mod validate;
-
@@ -6,7 +6,7 @@ use axum::{
     http::StatusCode,
 };
 use serde::{Serialize, de::DeserializeOwned};
-use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
 
 use crate::error::{HandlerError, HandlerResult};
 
@@ -16,9 +16,11 @@ use crate::error::{HandlerError, HandlerResult};
 pub struct Validated<T>(pub T);
 
 /// Error response for validation errors.
-/// 
+///
 /// This is used to return validation errors in a structured format. The format
-/// is a map of field names to a list of errors for that field.
+/// is a map of field names to a list of errors for that field. Fields of
+/// nested structs and lists are named by their path, e.g. `address.city` or
+/// `emails[0]`.
 #[derive(Serialize, Debug, Clone)]
 pub struct ValidationErrorResponse {
     pub validation_errors: HashMap<Cow<'static, str>, FieldErrors>,
@@ -26,31 +28,74 @@ pub struct ValidationErrorResponse {
 
 /// Errors in the individual fields.
 #[derive(Serialize, Debug, Clone)]
-pub struct FieldErrors(Vec<Cow<'static, str>>);
+pub struct FieldErrors(Vec<FieldError>);
 
+/// A single error in a field.
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldError {
+    /// A stable identifier for the error, which clients can use to show their
+    /// own messages.
+    pub code: Cow<'static, str>,
+    pub message: Cow<'static, str>,
+    /// Details about the error, like the allowed length.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub params: HashMap<Cow<'static, str>, serde_json::Value>,
+}
 
 impl From<ValidationErrors> for ValidationErrorResponse {
-    fn from(ValidationErrors(errors): ValidationErrors) -> Self {
+    fn from(errors: ValidationErrors) -> Self {
         let mut error_map = HashMap::new();
+        collect_errors(None, errors, &mut error_map);
 
-        for (field, kind) in errors {
-            error_map.insert(field.into(), kind.into());
+        Self {
+            validation_errors: error_map,
         }
+    }
+}
 
-        Self { validation_errors: error_map }
+impl From<ValidationError> for FieldError {
+    /// Turns a [`ValidationError`] into a [`FieldError`].
+    ///
+    /// The `value` param is left out, since [`validator`] adds the value of
+    /// the field to it, which could be a password.
+    fn from(mut err: ValidationError) -> Self {
+        err.params.remove("value");
+        Self {
+            message: err
+                .message
+                .unwrap_or_else(|| "Unkown validation error".into()),
+            code: err.code,
+            params: err.params,
+        }
     }
 }
 
-impl From<ValidationErrorsKind> for FieldErrors {
-    fn from(err: ValidationErrorsKind) -> Self {
-        match err {
-            ValidationErrorsKind::Field(errors) => FieldErrors(
-                errors
-                    .into_iter()
-                    .map(|e| e.message.unwrap_or_else(|| "Unkown validation error".into()))
-                    .collect(),
-            ),
-            _ => todo!(),
+/// Adds the errors of every field to the map, including the ones of nested
+/// structs and lists, whose fields are prefixed with `path`.
+fn collect_errors(
+    path: Option<&str>,
+    ValidationErrors(errors): ValidationErrors,
+    error_map: &mut HashMap<Cow<'static, str>, FieldErrors>,
+) {
+    for (field, kind) in errors {
+        let field = match path {
+            Some(path) => Cow::Owned(format!("{path}.{field}")),
+            None => field,
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                let errors = errors.into_iter().map(FieldError::from).collect();
+                error_map.insert(field, FieldErrors(errors));
+            }
+            ValidationErrorsKind::Struct(errors) => {
+                collect_errors(Some(&field), *errors, error_map);
+            }
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_errors(Some(&format!("{field}[{index}]")), *errors, error_map);
+                }
+            }
         }
     }
 }
@@ -83,17 +128,25 @@ where
     }
 }
 
+/// Returns a `HandlerError` with the errors of each field.
+///
+/// This makes it possible for handlers to return validation errors that are
+/// found after the request has been extracted, in the same format as the ones
+/// returned by [`Validated`].
+pub fn validation_failed(errors: ValidationErrors) -> HandlerError<ValidationErrorResponse> {
+    HandlerError::new(
+        StatusCode::BAD_REQUEST,
+        "Validation failed",
+        "Couldn't validate request body.",
+    )
+    .with_extension(ValidationErrorResponse::from(errors))
+}
+
 /// Validates the given data.
 #[inline]
+#[allow(clippy::result_large_err)]
 fn validate<T: Validate>(data: T) -> HandlerResult<(), ValidationErrorResponse> {
-    data.validate().map_err(|err| {
-        HandlerError::new(
-            StatusCode::BAD_REQUEST,
-            "Validation failed",
-            "Couldn't validate request body.",
-        )
-        .with_extension(ValidationErrorResponse::from(err))
-    })
+    data.validate().map_err(validation_failed)
 }
 
 /// Returns a `HandlerError` for unparseable requests.
@@ -105,3 +158,54 @@ fn unparseable<T: std::error::Error>(_: T) -> HandlerError<ValidationErrorRespon
         "Couldn't parse request body.",
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use validator::Validate;
+
+    use super::*;
+
+    #[derive(Validate)]
+    struct Address {
+        #[validate(length(min = 1, message = "City is required."))]
+        city: String,
+    }
+
+    #[derive(Validate)]
+    struct Signup {
+        #[validate(length(min = 12, message = "Password is too short."))]
+        password: String,
+        #[validate(nested)]
+        address: Address,
+    }
+
+    #[test]
+    fn test_validation_error_response() {
+        let signup = Signup {
+            password: "hunter2".into(),
+            address: Address {
+                city: String::new(),
+            },
+        };
+        let response = ValidationErrorResponse::from(signup.validate().unwrap_err());
+
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            json!({
+                "validation_errors": {
+                    "password": [{
+                        "code": "length",
+                        "message": "Password is too short.",
+                        "params": { "min": 12 },
+                    }],
+                    "address.city": [{
+                        "code": "length",
+                        "message": "City is required.",
+                        "params": { "min": 1 },
+                    }],
+                },
+            })
+        );
+    }
+}
@@ -1,23 +1,56 @@
 //! Claims for a JWT token.
+//!
+//! [`Claims`] holds the registered claims that every token has. Tokens that
+//! carry more than that use their own claims type, which embeds [`Claims`]
+//! with `#[serde(flatten)]` and implements [`HasClaims`]:
+//!
+//! ```rust
+//! use lerpz_jwt::{Claims, HasClaims};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct DeviceClaims {
+//!     #[serde(flatten)]
+//!     claims: Claims,
+//!     device_id: String,
+//! }
+//!
+//! impl HasClaims for DeviceClaims {
+//!     fn claims(&self) -> &Claims {
+//!         &self.claims
+//!     }
+//! }
+//! ```
 
-use chrono::Utc;
+use std::collections::BTreeSet;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use jsonwebtoken::Algorithm;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
 use uuid::Uuid;
 
+/// How long a token is valid by default.
+pub const DEFAULT_LIFETIME: Duration = Duration::minutes(15);
+
 /// Represent all claims for a token.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Claims {
-    /// What audience the token is for.
-    #[serde(skip_serializing_if = "String::is_empty")]
-    pub aud: String,
+    /// What audiences the token is for.
+    ///
+    /// Written as a single string when there is one audience, and as a list
+    /// otherwise.
+    #[serde(default, with = "one_or_many", skip_serializing_if = "Vec::is_empty")]
+    pub aud: Vec<String>,
     /// Who issued the token.
-    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub iss: String,
     /// Subject of the token.
-    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub sub: String,
     /// Unique identifier for the token.
-    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub jti: String,
     /// Which time the token will expire.
     pub exp: i64,
@@ -25,23 +58,160 @@ pub struct Claims {
     pub nbf: i64,
     /// When the token was issued.
     pub iat: i64,
-    /// Scope of the token.
-    #[serde(skip_serializing_if = "String::is_empty")]
-    pub scp: String
+    /// Scopes of the token.
+    ///
+    /// Written as a space separated string, but lists are accepted too.
+    #[serde(
+        default,
+        with = "space_separated",
+        skip_serializing_if = "BTreeSet::is_empty"
+    )]
+    pub scp: BTreeSet<String>,
+}
+
+/// A claims type that embeds the registered [`Claims`].
+pub trait HasClaims {
+    /// Returns the registered claims.
+    fn claims(&self) -> &Claims;
+
+    /// Returns the roles granted by the token, if the claims have any.
+    fn roles(&self) -> Option<&BTreeSet<String>> {
+        None
+    }
+}
+
+/// Claims for tokens issued to a user.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserClaims {
+    #[serde(flatten)]
+    pub claims: Claims,
+    /// The organization the user belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<Uuid>,
+    /// The username of the user.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub username: String,
+    /// The department of the user within the organization.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub department: Option<String>,
+    /// Roles granted to the user.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub roles: BTreeSet<String>,
+    /// How the user authenticated, e.g. `pwd` or `otp`.
+    ///
+    /// See [RFC 8176](https://www.rfc-editor.org/rfc/rfc8176) for the values.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+}
+
+/// Claims for OpenID Connect ID tokens.
+///
+/// See [OpenID Connect Core 1.0, section 2](https://openid.net/specs/openid-connect-core-1_0.html#IDToken).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IdTokenClaims {
+    #[serde(flatten)]
+    pub claims: Claims,
+    /// When the user authenticated, as a Unix timestamp.
+    pub auth_time: i64,
+    /// The nonce sent by the client in the authorization request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// How the user authenticated, see [`UserClaims::amr`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    /// Hash of the access token issued with the ID token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at_hash: Option<String>,
+}
+
+impl Claims {
+    /// Sets when the token expires, counted from now.
+    ///
+    /// This also sets `iat` and `nbf` to now.
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        let now = Utc::now().timestamp();
+        self.iat = now;
+        self.nbf = now;
+        self.exp = now + lifetime.num_seconds();
+        self
+    }
+
+    /// Sets who issued the token.
+    pub fn with_issuer(mut self, iss: impl Into<String>) -> Self {
+        self.iss = iss.into();
+        self
+    }
+
+    /// Sets the subject of the token.
+    pub fn with_subject(mut self, sub: impl Into<String>) -> Self {
+        self.sub = sub.into();
+        self
+    }
+
+    /// Adds an audience to the token.
+    pub fn with_audience(mut self, aud: impl Into<String>) -> Self {
+        let aud = aud.into();
+        if !self.aud.contains(&aud) {
+            self.aud.push(aud);
+        }
+        self
+    }
+
+    /// Adds audiences to the token.
+    pub fn with_audiences<I, S>(self, auds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        auds.into_iter().fold(self, Self::with_audience)
+    }
+
+    /// Adds a scope to the token.
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scp.insert(scope.into());
+        self
+    }
+
+    /// Adds scopes to the token.
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scp.extend(scopes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether the token is for the given audience.
+    pub fn has_audience(&self, aud: &str) -> bool {
+        self.aud.iter().any(|a| a == aud)
+    }
+
+    /// Whether the token has the given scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scp.contains(scope)
+    }
 }
 
 impl Default for Claims {
     fn default() -> Self {
         Claims {
-            aud: String::new(),
+            aud: Vec::new(),
             iss: String::new(),
             sub: String::new(),
             jti: Uuid::new_v4().to_string(),
-            exp: Utc::now().timestamp() + 900,
-            nbf: Utc::now().timestamp(),
-            iat: Utc::now().timestamp(),
-            scp: String::new(),
+            exp: 0,
+            nbf: 0,
+            iat: 0,
+            scp: BTreeSet::new(),
         }
+        .with_lifetime(DEFAULT_LIFETIME)
+    }
+}
+
+impl HasClaims for Claims {
+    fn claims(&self) -> &Claims {
+        self
     }
 }
 
@@ -53,3 +223,211 @@ impl From<lerpz_model::User> for Claims {
         }
     }
 }
+
+impl UserClaims {
+    /// Replaces the registered claims, keeping the user claims.
+    pub fn with_claims(mut self, f: impl FnOnce(Claims) -> Claims) -> Self {
+        self.claims = f(self.claims);
+        self
+    }
+}
+
+impl HasClaims for UserClaims {
+    fn claims(&self) -> &Claims {
+        &self.claims
+    }
+
+    fn roles(&self) -> Option<&BTreeSet<String>> {
+        Some(&self.roles)
+    }
+}
+
+impl From<lerpz_model::User> for UserClaims {
+    fn from(user: lerpz_model::User) -> Self {
+        UserClaims {
+            org_id: user.organization_id,
+            username: user.username.clone(),
+            department: None,
+            roles: BTreeSet::new(),
+            amr: Vec::new(),
+            claims: user.into(),
+        }
+    }
+}
+
+impl IdTokenClaims {
+    /// Creates ID token claims for a user that authenticated at `auth_time`.
+    pub fn new(claims: Claims, auth_time: i64) -> Self {
+        Self {
+            claims,
+            auth_time,
+            nonce: None,
+            amr: Vec::new(),
+            at_hash: None,
+        }
+    }
+
+    /// Sets the nonce sent by the client.
+    pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// Sets how the user authenticated.
+    pub fn with_amr<I, S>(mut self, amr: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.amr = amr.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Binds the ID token to the access token issued with it.
+    ///
+    /// The ID token must be signed with `algorithm`.
+    pub fn with_access_token(mut self, access_token: &str, algorithm: Algorithm) -> Self {
+        self.at_hash = Some(token_hash(access_token, algorithm));
+        self
+    }
+}
+
+impl HasClaims for IdTokenClaims {
+    fn claims(&self) -> &Claims {
+        &self.claims
+    }
+}
+
+/// Hashes a token for the `at_hash` or `c_hash` claim.
+///
+/// The token is hashed with the hash function of the algorithm, and the left
+/// half of the hash is base64url encoded. EdDSA uses SHA-512, since that is
+/// what Ed25519 uses internally.
+pub fn token_hash(token: &str, algorithm: Algorithm) -> String {
+    let hash = match algorithm {
+        Algorithm::HS384 | Algorithm::RS384 | Algorithm::PS384 | Algorithm::ES384 => {
+            Sha384::digest(token).to_vec()
+        }
+        Algorithm::HS512 | Algorithm::RS512 | Algorithm::PS512 | Algorithm::EdDSA => {
+            Sha512::digest(token).to_vec()
+        }
+        _ => Sha256::digest(token).to_vec(),
+    };
+    URL_SAFE_NO_PAD.encode(&hash[..hash.len() / 2])
+}
+
+/// A single string or a list of strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// (De)serializes a list as a single string when it has one entry.
+mod one_or_many {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::OneOrMany;
+
+    pub fn serialize<S: Serializer>(value: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            [one] => one.serialize(serializer),
+            many => many.serialize(serializer),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(one) => vec![one],
+            OneOrMany::Many(many) => many,
+        })
+    }
+}
+
+/// (De)serializes a set as a space separated string.
+mod space_separated {
+    use std::collections::BTreeSet;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::OneOrMany;
+
+    pub fn serialize<S: Serializer>(
+        value: &BTreeSet<String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let value: Vec<_> = value.iter().map(String::as_str).collect();
+        serializer.serialize_str(&value.join(" "))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeSet<String>, D::Error> {
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(one) => one.split_whitespace().map(String::from).collect(),
+            OneOrMany::Many(many) => many.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claims_wire_format() {
+        let claims = Claims::default()
+            .with_audience("portal")
+            .with_scopes(["user.read", "dept.read"]);
+        let json = serde_json::to_value(&claims).unwrap();
+        assert_eq!(json["aud"], "portal");
+        assert_eq!(json["scp"], "dept.read user.read");
+
+        let claims = claims.with_audience("entra");
+        let json = serde_json::to_value(&claims).unwrap();
+        assert_eq!(json["aud"], serde_json::json!(["portal", "entra"]));
+        assert_eq!(serde_json::from_value::<Claims>(json).unwrap(), claims);
+
+        let json = serde_json::json!({
+            "exp": 0, "nbf": 0, "iat": 0, "scp": ["a", "b"],
+        });
+        let claims: Claims = serde_json::from_value(json).unwrap();
+        assert!(claims.has_scope("a") && claims.has_scope("b"));
+        assert!(claims.aud.is_empty());
+    }
+
+    #[test]
+    fn test_user_claims_flatten() {
+        let claims = UserClaims {
+            claims: Claims::default().with_subject("user"),
+            org_id: Some(Uuid::nil()),
+            username: "kasper".into(),
+            department: Some("it".into()),
+            roles: ["admin".to_string()].into(),
+            amr: vec!["pwd".into()],
+        };
+        let json = serde_json::to_value(&claims).unwrap();
+        assert_eq!(json["sub"], "user");
+        assert_eq!(json["username"], "kasper");
+
+        let decoded: UserClaims = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, claims);
+    }
+
+    #[test]
+    fn test_token_hash() {
+        // Example from OpenID Connect Core 1.0, appendix A.3.
+        let access_token = "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y";
+        assert_eq!(
+            token_hash(access_token, Algorithm::RS256),
+            "77QmUPtjPfzWtF2AnpK9RQ"
+        );
+
+        let claims = IdTokenClaims::new(Claims::default(), 0)
+            .with_access_token(access_token, Algorithm::EdDSA);
+        assert_eq!(claims.at_hash.map(|hash| hash.len()), Some(43));
+    }
+}
@@ -9,4 +9,28 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
 	#[error(transparent)]
 	TokenError(#[from] jsonwebtoken::errors::Error),
+	#[error("no key with id \"{0}\" exist")]
+	KeyNotFound(String),
+	#[error("a key with id \"{0}\" already exist")]
+	KeyAlreadyExists(String),
+	#[error("invalid key \"{0}\": {1}")]
+	InvalidKey(String, String),
+	#[error("algorithm {0:?} is not supported")]
+	UnsupportedAlgorithm(jsonwebtoken::Algorithm),
+	#[error("no active key to sign with")]
+	NoActiveKey,
+	#[error("token has no \"kid\" header")]
+	MissingKid,
+	#[error("token has no \"{0}\" claim")]
+	MissingClaim(String),
+	#[error("token was issued too long ago")]
+	TokenTooOld,
+	#[error("token claims are invalid: {0}")]
+	InvalidClaims(#[from] serde_json::Error),
+	#[error("\"{0}\" must contain at least one entry")]
+	EmptyList(String),
+	#[error("failed reading key: {0}")]
+	Io(std::io::Error),
+	#[error("failed loading configuration: {0}")]
+	Env(#[from] lerpz_utils::env::Error),
 }
@@ -0,0 +1,557 @@
+//! Signing keys identified by a `kid`, and rotation between them.
+//!
+//! A [`KeyRing`] holds every key a service knows about. One of them is the
+//! active key, which is used to sign new tokens. The other keys are only used
+//! for verifying tokens, which makes it possible to rotate keys:
+//!
+//! 1. Add the new key and make it active. New tokens are signed with it.
+//! 2. Retire the old key with a deadline that is later than the expiry of
+//!    the last token it signed. It's still accepted until then.
+//! 3. Remove the old key once the deadline has passed.
+//!
+//! Keys are asymmetric (RS256, ES256 or EdDSA), so services that only verify
+//! tokens can do so with the public keys alone.
+
+use std::{collections::HashMap, fmt, path::Path, str::FromStr, sync::Arc};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+};
+use p256::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey};
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey, EncodeRsaPrivateKey},
+    traits::PublicKeyParts,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    VerifierConfig,
+    error::{Error, Result},
+};
+
+/// A key used for signing and verifying tokens.
+#[derive(Clone)]
+pub struct Key {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    jwk: Jwk,
+    retired_until: Option<DateTime<Utc>>,
+}
+
+/// A set of keys with one active key for signing.
+///
+/// This is cheap to clone, since the keys are shared between the clones.
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// use lerpz_jwt::{Algorithm, Claims, Key, KeyRing, TokenData, VerifierConfig};
+///
+/// let mut ring = KeyRing::new();
+/// ring.insert(Key::from_pem_file("2025-10", Algorithm::EdDSA, "keys/2025-10.pem")?)?;
+/// ring.insert(Key::from_pem_file("2025-04", Algorithm::EdDSA, "keys/2025-04.pem")?)?;
+/// ring.set_active("2025-10")?;
+/// ring.retire("2025-04", "2025-10-20T00:00:00Z".parse().unwrap())?;
+///
+/// let config = VerifierConfig::new("https://api.lerpz.local", "portal");
+/// let claims = Claims::default()
+///     .with_issuer("https://api.lerpz.local")
+///     .with_audience("portal")
+///     .with_subject("user");
+///
+/// let token = ring.encode(&claims)?;
+/// let data: TokenData<Claims> = ring.decode(&token, &config)?;
+/// # Ok::<(), lerpz_jwt::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct KeyRing {
+    keys: HashMap<String, Arc<Key>>,
+    active: Option<String>,
+}
+
+impl Key {
+    /// Creates a key from a PEM encoded private or public key.
+    ///
+    /// Private keys can be used for both signing and verifying, while public
+    /// keys can only be used for verifying. The supported formats are:
+    /// - `RS256`, `RS384`, `RS512`: PKCS#1 or PKCS#8 private keys, and PKCS#1
+    ///   or SPKI public keys.
+    /// - `ES256`: SEC1 or PKCS#8 private keys on P-256, and SPKI public keys.
+    /// - `EdDSA`: PKCS#8 Ed25519 private keys, and SPKI public keys.
+    pub fn from_pem(kid: impl Into<String>, algorithm: Algorithm, pem: &str) -> Result<Self> {
+        let kid = kid.into();
+        let private = pem.contains("PRIVATE KEY");
+
+        let (encoding, params) = match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+                let (encoding, public) = if private {
+                    let key = rsa::RsaPrivateKey::from_pkcs8_pem(pem)
+                        .or_else(|_| rsa::RsaPrivateKey::from_pkcs1_pem(pem))
+                        .map_err(|err| invalid_key(&kid, err))?;
+                    let der = key.to_pkcs1_der().map_err(|err| invalid_key(&kid, err))?;
+                    (
+                        Some(EncodingKey::from_rsa_der(der.as_bytes())),
+                        key.to_public_key(),
+                    )
+                } else {
+                    let key = rsa::RsaPublicKey::from_public_key_pem(pem)
+                        .or_else(|_| rsa::RsaPublicKey::from_pkcs1_pem(pem))
+                        .map_err(|err| invalid_key(&kid, err))?;
+                    (None, key)
+                };
+
+                let params = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(public.e().to_bytes_be()),
+                });
+                (encoding, params)
+            }
+            Algorithm::ES256 => {
+                let (encoding, public) = if private {
+                    let key = p256::SecretKey::from_pkcs8_pem(pem)
+                        .or_else(|_| p256::SecretKey::from_sec1_pem(pem))
+                        .map_err(|err| invalid_key(&kid, err))?;
+                    let der = key.to_pkcs8_der().map_err(|err| invalid_key(&kid, err))?;
+                    (
+                        Some(EncodingKey::from_ec_der(der.as_bytes())),
+                        key.public_key(),
+                    )
+                } else {
+                    let key = p256::PublicKey::from_public_key_pem(pem)
+                        .map_err(|err| invalid_key(&kid, err))?;
+                    (None, key)
+                };
+
+                let point = p256::EncodedPoint::from(public);
+                let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                    return Err(invalid_key(&kid, "public key is not uncompressed"));
+                };
+                let params = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(x),
+                    y: URL_SAFE_NO_PAD.encode(y),
+                });
+                (encoding, params)
+            }
+            Algorithm::EdDSA => {
+                let (encoding, public) = if private {
+                    let key = ed25519_dalek::SigningKey::from_pkcs8_pem(pem)
+                        .map_err(|err| invalid_key(&kid, err))?;
+                    let der = key.to_pkcs8_der().map_err(|err| invalid_key(&kid, err))?;
+                    (
+                        Some(EncodingKey::from_ed_der(der.as_bytes())),
+                        key.verifying_key(),
+                    )
+                } else {
+                    let key = ed25519_dalek::VerifyingKey::from_public_key_pem(pem)
+                        .map_err(|err| invalid_key(&kid, err))?;
+                    (None, key)
+                };
+
+                let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(public.as_bytes()),
+                });
+                (encoding, params)
+            }
+            algorithm => return Err(Error::UnsupportedAlgorithm(algorithm)),
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm(algorithm)),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: params,
+        };
+        let decoding = DecodingKey::from_jwk(&jwk)?;
+
+        Ok(Self {
+            kid,
+            algorithm,
+            encoding,
+            decoding,
+            jwk,
+            retired_until: None,
+        })
+    }
+
+    /// Same as [`Key::from_pem`], but reads the PEM from a file.
+    pub fn from_pem_file(
+        kid: impl Into<String>,
+        algorithm: Algorithm,
+        path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let pem = std::fs::read_to_string(path).map_err(Error::Io)?;
+        Self::from_pem(kid, algorithm, &pem)
+    }
+
+    /// The id of the key, which is put in the `kid` header of tokens.
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    /// The algorithm the key signs with.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Whether the key can sign tokens, which requires the private key.
+    pub fn can_sign(&self) -> bool {
+        self.encoding.is_some()
+    }
+
+    /// When the key stops being accepted, if it's retired.
+    pub fn retired_until(&self) -> Option<DateTime<Utc>> {
+        self.retired_until
+    }
+
+    /// Whether tokens signed with the key are still accepted.
+    pub fn is_usable(&self) -> bool {
+        self.retired_until.is_none_or(|until| Utc::now() < until)
+    }
+
+    /// The public part of the key as a JSON Web Key.
+    pub fn jwk(&self) -> &Jwk {
+        &self.jwk
+    }
+}
+
+impl fmt::Debug for Key {
+    /// Doesn't show the key material, so that it doesn't end up in logs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("can_sign", &self.can_sign())
+            .field("retired_until", &self.retired_until)
+            .finish()
+    }
+}
+
+impl KeyRing {
+    /// Creates an empty key ring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the keys from environment variables.
+    ///
+    /// - `JWT_KEYS`: Comma separated list of `<kid>:<algorithm>:<path>`,
+    ///   where `<path>` is a PEM file.
+    /// - `JWT_ACTIVE_KID`: The id of the key used for signing. Optional for
+    ///   services that only verify tokens.
+    /// - `JWT_RETIRED_KIDS`: Optional comma separated list of
+    ///   `<kid>@<RFC 3339 deadline>`.
+    pub fn from_env() -> Result<Self> {
+        let mut ring = Self::new();
+
+        for entry in list(&lerpz_utils::get_env("JWT_KEYS")?) {
+            let mut parts = entry.splitn(3, ':');
+            let (Some(kid), Some(algorithm), Some(path)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(Error::InvalidKey(
+                    entry.into(),
+                    "expected \"<kid>:<algorithm>:<path>\"".into(),
+                ));
+            };
+            ring.insert(Key::from_pem_file(
+                kid,
+                Algorithm::from_str(algorithm)?,
+                path,
+            )?)?;
+        }
+
+        if let Ok(kid) = lerpz_utils::get_env("JWT_ACTIVE_KID") {
+            ring.set_active(&kid)?;
+        }
+
+        if let Ok(retired) = lerpz_utils::get_env("JWT_RETIRED_KIDS") {
+            for entry in list(&retired) {
+                let (kid, until) = entry.split_once('@').ok_or_else(|| {
+                    Error::InvalidKey(entry.into(), "expected \"<kid>@<deadline>\"".into())
+                })?;
+                let until = until
+                    .parse()
+                    .map_err(|err| Error::InvalidKey(kid.into(), format!("{err}")))?;
+                ring.retire(kid, until)?;
+            }
+        }
+
+        Ok(ring)
+    }
+
+    /// Adds a key to the ring.
+    ///
+    /// Returns an error if a key with the same id already exists.
+    pub fn insert(&mut self, key: Key) -> Result<()> {
+        if self.keys.contains_key(&key.kid) {
+            return Err(Error::KeyAlreadyExists(key.kid));
+        }
+
+        self.keys.insert(key.kid.clone(), Arc::new(key));
+        Ok(())
+    }
+
+    /// Removes a key from the ring.
+    ///
+    /// Tokens signed with the key are no longer accepted.
+    pub fn remove(&mut self, kid: &str) -> Result<()> {
+        if self.active.as_deref() == Some(kid) {
+            return Err(Error::InvalidKey(
+                kid.into(),
+                "the active key can't be removed".into(),
+            ));
+        }
+
+        self.keys
+            .remove(kid)
+            .map(|_| ())
+            .ok_or_else(|| Error::KeyNotFound(kid.into()))
+    }
+
+    /// Sets which key is used for signing new tokens.
+    ///
+    /// Returns an error if the key doesn't exist, is retired or has no
+    /// private key.
+    pub fn set_active(&mut self, kid: &str) -> Result<()> {
+        let key = self
+            .keys
+            .get(kid)
+            .ok_or_else(|| Error::KeyNotFound(kid.into()))?;
+
+        if !key.can_sign() {
+            return Err(Error::InvalidKey(kid.into(), "no private key".into()));
+        }
+        if key.retired_until.is_some() {
+            return Err(Error::InvalidKey(kid.into(), "the key is retired".into()));
+        }
+
+        self.active = Some(kid.into());
+        Ok(())
+    }
+
+    /// Stops using the key for anything but verifying tokens until the given
+    /// deadline.
+    pub fn retire(&mut self, kid: &str, until: DateTime<Utc>) -> Result<()> {
+        if self.active.as_deref() == Some(kid) {
+            return Err(Error::InvalidKey(
+                kid.into(),
+                "the active key can't be retired".into(),
+            ));
+        }
+
+        let key = self
+            .keys
+            .get_mut(kid)
+            .ok_or_else(|| Error::KeyNotFound(kid.into()))?;
+        Arc::make_mut(key).retired_until = Some(until);
+        Ok(())
+    }
+
+    /// Returns the key used for signing new tokens.
+    pub fn active(&self) -> Option<&Key> {
+        self.active.as_deref().and_then(|kid| self.get(kid))
+    }
+
+    /// Returns the key with the given id, if it's still accepted.
+    pub fn get(&self, kid: &str) -> Option<&Key> {
+        self.keys
+            .get(kid)
+            .map(Arc::as_ref)
+            .filter(|key| key.is_usable())
+    }
+
+    /// Returns all keys that are still accepted.
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.keys
+            .values()
+            .map(Arc::as_ref)
+            .filter(|key| key.is_usable())
+    }
+
+    /// Returns the public parts of all keys that are still accepted.
+    pub fn jwks(&self) -> JwkSet {
+        let mut keys: Vec<_> = self.keys().collect();
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+        JwkSet {
+            keys: keys.into_iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+
+    /// Signs the claims with the active key.
+    ///
+    /// The `kid` and `alg` headers are set to the ones of the active key.
+    pub fn encode<C: Serialize>(&self, claims: &C) -> Result<String> {
+        let key = self.active().ok_or(Error::NoActiveKey)?;
+        let encoding = key.encoding.as_ref().ok_or(Error::NoActiveKey)?;
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        Ok(encode(&header, claims, encoding)?)
+    }
+
+    /// Verifies a token with the key named in its `kid` header.
+    ///
+    /// Returns an error if the key is unknown or retired past its deadline,
+    /// if the `alg` header doesn't match the algorithm of the key, or if the
+    /// token doesn't follow the config.
+    pub fn decode<C: DeserializeOwned>(
+        &self,
+        token: &str,
+        config: &VerifierConfig,
+    ) -> Result<TokenData<C>> {
+        let header = decode_header(token)?;
+        let kid = header.kid.ok_or(Error::MissingKid)?;
+        let key = self.get(&kid).ok_or(Error::KeyNotFound(kid))?;
+
+        if header.alg != key.algorithm {
+            return Err(Error::UnsupportedAlgorithm(header.alg));
+        }
+        config.decode(token, &key.decoding)
+    }
+}
+
+impl fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("keys", &self.keys.values().collect::<Vec<_>>())
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+/// Returns the non-empty entries of a comma separated list.
+fn list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+/// Returns an error for a key that couldn't be loaded.
+fn invalid_key(kid: &str, err: impl fmt::Display) -> Error {
+    Error::InvalidKey(kid.into(), err.to_string())
+}
+
+/// The JWK `alg` of a signing algorithm.
+fn key_algorithm(algorithm: Algorithm) -> KeyAlgorithm {
+    match algorithm {
+        Algorithm::HS256 => KeyAlgorithm::HS256,
+        Algorithm::HS384 => KeyAlgorithm::HS384,
+        Algorithm::HS512 => KeyAlgorithm::HS512,
+        Algorithm::ES256 => KeyAlgorithm::ES256,
+        Algorithm::ES384 => KeyAlgorithm::ES384,
+        Algorithm::RS256 => KeyAlgorithm::RS256,
+        Algorithm::RS384 => KeyAlgorithm::RS384,
+        Algorithm::RS512 => KeyAlgorithm::RS512,
+        Algorithm::PS256 => KeyAlgorithm::PS256,
+        Algorithm::PS384 => KeyAlgorithm::PS384,
+        Algorithm::PS512 => KeyAlgorithm::PS512,
+        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::pkcs8::{EncodePublicKey, LineEnding};
+
+    use super::*;
+    use crate::Claims;
+
+    fn config() -> VerifierConfig {
+        VerifierConfig::new("lerpz", "portal")
+    }
+
+    fn claims() -> Claims {
+        Claims::default()
+            .with_issuer("lerpz")
+            .with_audience("portal")
+            .with_subject("user")
+    }
+
+    /// Creates PEM encoded Ed25519 private and public keys from a seed.
+    fn ed25519_pem(seed: u8) -> (String, String) {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[seed; 32]);
+        (
+            key.to_pkcs8_pem(LineEnding::LF).unwrap().to_string(),
+            key.verifying_key()
+                .to_public_key_pem(LineEnding::LF)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_keyring_rotation() {
+        let (new_private, new_public) = ed25519_pem(1);
+        let (old_private, _) = ed25519_pem(2);
+
+        let mut ring = KeyRing::new();
+        ring.insert(Key::from_pem("old", Algorithm::EdDSA, &old_private).unwrap())
+            .unwrap();
+        ring.set_active("old").unwrap();
+        let old_token = ring.encode(&claims()).unwrap();
+
+        ring.insert(Key::from_pem("new", Algorithm::EdDSA, &new_private).unwrap())
+            .unwrap();
+        ring.set_active("new").unwrap();
+        ring.retire("old", Utc::now() + chrono::Duration::hours(1))
+            .unwrap();
+        let new_token = ring.encode(&claims()).unwrap();
+
+        assert!(ring.decode::<Claims>(&old_token, &config()).is_ok());
+        assert_eq!(
+            ring.decode::<Claims>(&new_token, &config())
+                .unwrap()
+                .header
+                .kid
+                .unwrap(),
+            "new"
+        );
+        assert_eq!(ring.jwks().keys.len(), 2);
+
+        ring.retire("old", Utc::now() - chrono::Duration::hours(1))
+            .unwrap();
+        assert!(matches!(
+            ring.decode::<Claims>(&old_token, &config()),
+            Err(Error::KeyNotFound(_))
+        ));
+
+        let mut verifier = KeyRing::new();
+        verifier
+            .insert(Key::from_pem("new", Algorithm::EdDSA, &new_public).unwrap())
+            .unwrap();
+        assert!(verifier.set_active("new").is_err());
+        assert!(verifier.decode::<Claims>(&new_token, &config()).is_ok());
+    }
+
+    #[test]
+    fn test_es256_key() {
+        let secret = p256::SecretKey::from_slice(&[7; 32]).unwrap();
+        let pem = secret.to_pkcs8_pem(LineEnding::LF).unwrap();
+
+        let mut ring = KeyRing::new();
+        ring.insert(Key::from_pem("ec", Algorithm::ES256, &pem).unwrap())
+            .unwrap();
+        ring.set_active("ec").unwrap();
+
+        let token = ring.encode(&claims()).unwrap();
+        assert!(ring.decode::<Claims>(&token, &config()).is_ok());
+    }
+}
@@ -4,22 +4,23 @@
 pub mod claims;
 /// Errors that can occur when working with JWT tokens.
 pub mod error;
+/// Signing keys and rotation between them.
+pub mod keyring;
+/// Rules that tokens are verified against.
+pub mod verifier;
 
-pub use jsonwebtoken::{DecodingKey, EncodingKey};
-use jsonwebtoken::{Header, TokenData, Validation, decode, encode};
+pub use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, TokenData, jwk::JwkSet};
+use serde::de::DeserializeOwned;
 
-pub use claims::Claims;
+pub use claims::{Claims, HasClaims, IdTokenClaims, UserClaims};
 pub use error::{Error, Result};
+pub use keyring::{Key, KeyRing};
+pub use verifier::VerifierConfig;
 
-pub fn encode_jwt(claims: impl Into<Claims>, key: &EncodingKey) -> Result<String> {
-    let header = Header::default();
-    let claims = claims.into();
-    let token = encode(&header, &claims, key).map_err(Error::TokenError)?;
-    Ok(token)
-}
-
-pub fn decode_jwt(token: &str, key: &DecodingKey) -> Result<TokenData<Claims>> {
-    let validation = Validation::default();
-    let claims = decode::<Claims>(token, key, &validation).map_err(Error::TokenError)?;
-    Ok(claims)
+pub fn decode_jwt<C: DeserializeOwned>(
+    token: &str,
+    key: &DecodingKey,
+    config: &VerifierConfig,
+) -> Result<TokenData<C>> {
+    config.decode(token, key)
 }
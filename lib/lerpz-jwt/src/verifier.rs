@@ -0,0 +1,297 @@
+//! What a token must look like to be accepted.
+//!
+//! [`VerifierConfig`] turns into the [`Validation`] used by `jsonwebtoken`,
+//! and adds the checks it can't do on its own: requiring any claim, not just
+//! the registered ones, and rejecting tokens that were issued too long ago.
+
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, TokenData, Validation, decode, decode_header};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+
+/// The algorithms accepted by default.
+///
+/// These are the ones a [`Key`](crate::Key) can be made from.
+const DEFAULT_ALGORITHMS: [Algorithm; 5] = [
+    Algorithm::EdDSA,
+    Algorithm::ES256,
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+];
+
+/// The claims required by default.
+const DEFAULT_REQUIRED_CLAIMS: [&str; 6] = ["iss", "aud", "sub", "exp", "iat", "jti"];
+
+/// The rules a token is verified against.
+///
+/// There is no [`Default`], since a token must always be checked against at
+/// least one issuer and one audience. Everything else defaults to:
+///
+/// - Algorithms: EdDSA, ES256, RS256, RS384 and RS512.
+/// - Clock skew: 60 seconds.
+/// - Max age: none, only `exp` is checked.
+/// - Required claims: `iss`, `aud`, `sub`, `exp`, `iat` and `jti`.
+///
+/// ### Example
+///
+/// ```rust
+/// use chrono::Duration;
+/// use lerpz_jwt::VerifierConfig;
+///
+/// let config = VerifierConfig::new("https://api.lerpz.local", "portal")
+///     .with_audience("entra-mcp")
+///     .with_clock_skew(Duration::seconds(30))
+///     .with_max_age(Duration::hours(12))
+///     .with_required_claim("scp");
+/// ```
+#[derive(Debug, Clone)]
+pub struct VerifierConfig {
+    issuers: Vec<String>,
+    audiences: Vec<String>,
+    algorithms: Vec<Algorithm>,
+    clock_skew: Duration,
+    max_age: Option<Duration>,
+    required_claims: Vec<String>,
+}
+
+impl VerifierConfig {
+    /// Creates a config that accepts tokens from the issuer for the audience.
+    pub fn new(issuer: impl Into<String>, audience: impl Into<String>) -> Self {
+        Self {
+            issuers: vec![issuer.into()],
+            audiences: vec![audience.into()],
+            algorithms: DEFAULT_ALGORITHMS.to_vec(),
+            clock_skew: Duration::seconds(60),
+            max_age: None,
+            required_claims: DEFAULT_REQUIRED_CLAIMS.map(String::from).to_vec(),
+        }
+    }
+
+    /// Creates a config from environment variables.
+    ///
+    /// - `JWT_ISSUERS`: Comma separated list of accepted issuers.
+    /// - `JWT_AUDIENCES`: Comma separated list of accepted audiences.
+    ///
+    /// Returns an error if either list is empty.
+    pub fn from_env() -> Result<Self> {
+        let issuers = env_list("JWT_ISSUERS")?;
+        let audiences = env_list("JWT_AUDIENCES")?;
+
+        let mut config = Self::new(&issuers[0], &audiences[0]);
+        config.issuers = issuers;
+        config.audiences = audiences;
+        Ok(config)
+    }
+
+    /// Accepts tokens from another issuer too.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuers.push(issuer.into());
+        self
+    }
+
+    /// Accepts tokens for another audience too.
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    /// Replaces the algorithms tokens may be signed with.
+    pub fn with_algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = algorithms.into_iter().collect();
+        self
+    }
+
+    /// Sets how far the clocks of the issuer and this service may drift apart.
+    pub fn with_clock_skew(mut self, clock_skew: Duration) -> Self {
+        self.clock_skew = clock_skew;
+        self
+    }
+
+    /// Rejects tokens issued longer ago than this, even if they haven't
+    /// expired yet.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Requires the token to have another claim.
+    pub fn with_required_claim(mut self, claim: impl Into<String>) -> Self {
+        let claim = claim.into();
+        if !self.required_claims.contains(&claim) {
+            self.required_claims.push(claim);
+        }
+        self
+    }
+
+    /// Replaces the claims a token must have.
+    pub fn with_required_claims<I, S>(mut self, claims: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.required_claims = claims.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The accepted issuers.
+    pub fn issuers(&self) -> &[String] {
+        &self.issuers
+    }
+
+    /// The accepted audiences.
+    pub fn audiences(&self) -> &[String] {
+        &self.audiences
+    }
+
+    /// The algorithms tokens may be signed with.
+    pub fn algorithms(&self) -> &[Algorithm] {
+        &self.algorithms
+    }
+
+    /// Returns the [`Validation`] for a token signed with the algorithm.
+    ///
+    /// This only covers what `jsonwebtoken` checks itself. Use
+    /// [`VerifierConfig::decode`] to check everything.
+    pub fn validation(&self, algorithm: Algorithm) -> Result<Validation> {
+        if !self.algorithms.contains(&algorithm) {
+            return Err(Error::UnsupportedAlgorithm(algorithm));
+        }
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_required_spec_claims(&self.required_claims);
+        validation.set_issuer(&self.issuers);
+        validation.set_audience(&self.audiences);
+        validation.validate_exp = true;
+        validation.validate_nbf = true;
+        validation.leeway = self.clock_skew.num_seconds().max(0) as u64;
+        Ok(validation)
+    }
+
+    /// Verifies the token with the key and returns its claims.
+    pub fn decode<C: DeserializeOwned>(
+        &self,
+        token: &str,
+        key: &DecodingKey,
+    ) -> Result<TokenData<C>> {
+        let header = decode_header(token)?;
+        let validation = self.validation(header.alg)?;
+        let data = decode::<Map<String, Value>>(token, key, &validation)?;
+
+        if let Some(missing) = self
+            .required_claims
+            .iter()
+            .find(|claim| data.claims.get(*claim).is_none_or(Value::is_null))
+        {
+            return Err(Error::MissingClaim(missing.clone()));
+        }
+
+        if let Some(max_age) = self.max_age {
+            let iat = data
+                .claims
+                .get("iat")
+                .and_then(Value::as_i64)
+                .ok_or_else(|| Error::MissingClaim("iat".into()))?;
+            let age = Utc::now().timestamp() - iat;
+            if age > (max_age + self.clock_skew).num_seconds() {
+                return Err(Error::TokenTooOld);
+            }
+        }
+
+        Ok(TokenData {
+            header: data.header,
+            claims: serde_json::from_value(Value::Object(data.claims))?,
+        })
+    }
+}
+
+/// Reads a comma separated list that must have at least one entry.
+fn env_list(key: &str) -> Result<Vec<String>> {
+    let value = lerpz_utils::get_env(key)?;
+    let list: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect();
+    if list.is_empty() {
+        return Err(Error::EmptyList(key.into()));
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{EncodingKey, Header, encode};
+
+    use super::*;
+    use crate::Claims;
+
+    const SECRET: &[u8] = b"secret";
+
+    fn token(claims: &Claims) -> String {
+        encode(
+            &Header::new(Algorithm::HS256),
+            claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verifier_config() {
+        let config = VerifierConfig::new("lerpz", "portal").with_algorithms([Algorithm::HS256]);
+        let key = DecodingKey::from_secret(SECRET);
+        let claims = Claims::default()
+            .with_issuer("lerpz")
+            .with_audience("portal")
+            .with_subject("user");
+
+        let data = config.decode::<Claims>(&token(&claims), &key).unwrap();
+        assert_eq!(data.claims, claims);
+
+        let other_audience = claims.clone().with_audience("entra");
+        assert!(
+            config
+                .decode::<Claims>(&token(&other_audience), &key)
+                .is_ok()
+        );
+        let wrong_audience = Claims {
+            aud: vec!["entra".into()],
+            ..claims.clone()
+        };
+        assert!(
+            config
+                .decode::<Claims>(&token(&wrong_audience), &key)
+                .is_err()
+        );
+
+        let no_subject = claims.clone().with_subject("");
+        assert!(config.decode::<Claims>(&token(&no_subject), &key).is_err());
+        let config = config.with_required_claim("scp");
+        assert!(matches!(
+            config.decode::<Claims>(&token(&claims), &key),
+            Err(Error::MissingClaim(claim)) if claim == "scp"
+        ));
+        let with_scope = claims.clone().with_scope("user.read");
+        assert!(config.decode::<Claims>(&token(&with_scope), &key).is_ok());
+
+        let old = Claims {
+            iat: claims.iat - 7200,
+            ..with_scope
+        };
+        let config = config.with_max_age(Duration::hours(1));
+        assert!(matches!(
+            config.decode::<Claims>(&token(&old), &key),
+            Err(Error::TokenTooOld)
+        ));
+
+        let config = VerifierConfig::new("lerpz", "portal");
+        assert!(matches!(
+            config.decode::<Claims>(&token(&claims), &key),
+            Err(Error::UnsupportedAlgorithm(Algorithm::HS256))
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(FromRow, Debug, Clone)]
+pub struct UserConsent {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
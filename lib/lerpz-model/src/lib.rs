@@ -1,7 +1,9 @@
+pub mod consent;
 pub mod oauth;
 pub mod org;
 pub mod user;
 
+pub use consent::*;
 pub use oauth::*;
 pub use org::*;
 pub use user::*;
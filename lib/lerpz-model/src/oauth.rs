@@ -12,6 +12,17 @@ pub struct OAuthClient {
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(FromRow, Debug, Clone)]
+pub struct ClientSecret {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub secret_hash: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
 #[derive(FromRow, Debug, Clone)]
 pub struct RedirectUri {
     pub id: Uuid,
@@ -45,9 +56,13 @@ pub struct RefreshToken {
     pub token: String,
     pub user_id: Uuid,
     pub client_id: Uuid,
+    pub family_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub scope: Option<String>,
     pub expires_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
     pub revoked_at: Option<DateTime<Utc>>,
 }
 
@@ -57,7 +72,8 @@ pub struct AccessToken {
     pub jti: String,
     pub user_id: Option<Uuid>,
     pub client_id: Uuid,
-    pub scope: String,
+    pub family_id: Option<Uuid>,
+    pub scope: Option<String>,
     pub expires_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -8,7 +8,8 @@ pub struct User {
     pub username: String,
     pub primary_email: String,
     pub password_hash: String,
-    pub password_salt: String,
+    pub password_salt: Option<String>,
+    pub password_breached_at: Option<DateTime<Utc>>,
     pub avatar: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
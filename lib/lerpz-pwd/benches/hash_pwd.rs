@@ -1,13 +1,42 @@
 use criterion::{Criterion, criterion_group, criterion_main};
-use std::hint::black_box;
+use std::{hint::black_box, time::Duration};
 
-use lerpz_pwd::hash_pwd;
+use lerpz_pwd::{Scheme, Tuner, hash_pwd};
 
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("hash_pwd", |b| {
-        b.iter(|| hash_pwd(black_box("#Password123!"), black_box("some_salt")))
+        b.iter(|| hash_pwd(black_box("#Password123!")))
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+/// Benchmarks hashing with parameters tuned for the current machine.
+///
+/// The target latency in milliseconds can be set using the
+/// `PWD_TUNE_TARGET_MS` environment variable and defaults to 250ms.
+fn tuned_benchmark(c: &mut Criterion) {
+    let target = std::env::var("PWD_TUNE_TARGET_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .unwrap_or(250);
+
+    let scheme = Tuner::new(Duration::from_millis(target))
+        .tune_scheme()
+        .unwrap();
+    let params = scheme.params();
+    println!(
+        "tuned for {target}ms: m={},t={},p={}",
+        params.m_cost(),
+        params.t_cost(),
+        params.p_cost()
+    );
+
+    let mut group = c.benchmark_group("hash_pwd_tuned");
+    group.sample_size(10);
+    group.bench_function(format!("{target}ms"), |b| {
+        b.iter(|| scheme.hash(black_box("#Password123!")))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, tuned_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,207 @@
+//! Checking passwords against a local dataset of breached passwords.
+//!
+//! The dataset is a text file with one `<SHA-1 hash>:<count>` pair per line,
+//! sorted by hash, like the one published by Have I Been Pwned:
+//!
+//! ```text
+//! 000000005AD76BD555C1D6D771DE417A4B87E4B4:10
+//! 00000000A8DAE4228F821FB418F59826079BF368:4
+//! ```
+//!
+//! The file is never loaded into memory. Instead it's binary searched, so a
+//! lookup only reads a few small parts of the file, even if it's many
+//! gigabytes large.
+
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use sha1::{Digest, Sha1};
+
+use crate::{
+    SecretString, Violation,
+    error::{Error, Result},
+};
+
+/// Looks up how many times a password has appeared in data breaches.
+///
+/// This is cheap to clone, since the clones share the same path.
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// use lerpz_pwd::{BreachChecker, PasswordPolicy, SecretString};
+///
+/// # async fn example() -> lerpz_pwd::Result<()> {
+/// let checker = BreachChecker::open("pwned-passwords-sha1-ordered-by-hash.txt")?;
+/// let pwd = SecretString::from("Password123!");
+///
+/// let mut violations = PasswordPolicy::default().check(pwd.expose_secret(), &[]);
+/// violations.extend(checker.check(&pwd).await?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BreachChecker {
+    path: Arc<Path>,
+}
+
+impl BreachChecker {
+    /// Uses the dataset at the given path.
+    ///
+    /// Returns an error if the file can't be opened.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path: PathBuf = path.into();
+        File::open(&path).map_err(Error::Breach)?;
+        Ok(Self { path: path.into() })
+    }
+
+    /// Uses the dataset at the path given by the `PWD_BREACH_FILE`
+    /// environment variable.
+    pub fn from_env() -> Result<Self> {
+        Self::open(lerpz_utils::get_env("PWD_BREACH_FILE")?)
+    }
+
+    /// Returns how many times the password has appeared in breaches.
+    ///
+    /// The lookup is done on a blocking thread, since it reads from disk.
+    pub async fn count(&self, pwd: &SecretString) -> Result<u64> {
+        let checker = self.clone();
+        let hash = sha1_hex(pwd.expose_secret());
+
+        tokio::task::spawn_blocking(move || checker.lookup(&hash))
+            .await
+            .map_err(|_| Error::FailSpawnBlockForBreachCheck)?
+    }
+
+    /// Same as [`BreachChecker::count`], but blocks the current thread.
+    pub fn count_blocking(&self, pwd: &SecretString) -> Result<u64> {
+        self.lookup(&sha1_hex(pwd.expose_secret()))
+    }
+
+    /// Returns a [`Violation::Breached`] if the password has appeared in
+    /// breaches.
+    ///
+    /// This is meant to be used together with [`crate::PasswordPolicy`].
+    pub async fn check(&self, pwd: &SecretString) -> Result<Option<Violation>> {
+        let count = self.count(pwd).await?;
+        Ok((count > 0).then_some(Violation::Breached { count }))
+    }
+
+    /// Binary searches the dataset for the given uppercase hex hash.
+    ///
+    /// `low` is always at the start of a line and the line that is searched
+    /// for always starts in `low..high`.
+    fn lookup(&self, hash: &str) -> Result<u64> {
+        let mut file = File::open(&self.path).map_err(Error::Breach)?;
+        let mut low = 0;
+        let mut high = file.metadata().map_err(Error::Breach)?.len();
+        let mut line = String::new();
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+
+            let (start, end) = read_line_from(&mut file, low, mid, &mut line)?;
+            if start >= high || line.is_empty() {
+                high = mid;
+                continue;
+            }
+
+            let (line_hash, count) = line
+                .split_once(':')
+                .ok_or_else(|| invalid_data(format!("invalid line at byte {start}")))?;
+
+            match line_hash.to_ascii_uppercase().as_str().cmp(hash) {
+                Ordering::Equal => {
+                    return count
+                        .trim()
+                        .parse()
+                        .map_err(|_| invalid_data(format!("invalid count at byte {start}")));
+                }
+                Ordering::Less => low = end,
+                Ordering::Greater => high = mid,
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+/// Reads the first line that starts at or after `pos` into `line`.
+///
+/// Returns the offsets of the start and the end of the line. The line is
+/// empty if there are no more lines.
+fn read_line_from(file: &mut File, low: u64, pos: u64, line: &mut String) -> Result<(u64, u64)> {
+    let mut start = pos;
+    let mut reader = if pos > low {
+        file.seek(SeekFrom::Start(pos - 1)).map_err(Error::Breach)?;
+        let mut reader = BufReader::new(&mut *file);
+        let mut skipped = Vec::new();
+        start += reader
+            .read_until(b'\n', &mut skipped)
+            .map_err(Error::Breach)? as u64
+            - 1;
+        reader
+    } else {
+        file.seek(SeekFrom::Start(pos)).map_err(Error::Breach)?;
+        BufReader::new(&mut *file)
+    };
+
+    line.clear();
+    let read = reader.read_line(line).map_err(Error::Breach)? as u64;
+    line.truncate(line.trim_end().len());
+
+    Ok((start, start + read))
+}
+
+/// Returns the uppercase hex SHA-1 hash of the password.
+fn sha1_hex(pwd: &str) -> String {
+    hex::encode_upper(Sha1::digest(pwd.as_bytes()))
+}
+
+/// Returns an error for a dataset that isn't formatted correctly.
+fn invalid_data(msg: String) -> Error {
+    Error::Breach(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_breach_lookup() {
+        let breached = ["password", "123456", "qwerty", "letmein", "dragon"];
+        let mut lines: Vec<String> = breached
+            .iter()
+            .enumerate()
+            .map(|(i, pwd)| format!("{}:{}\r\n", sha1_hex(pwd), i + 1))
+            .collect();
+        lines.sort();
+
+        let path = std::env::temp_dir().join(format!("lerpz-pwd-{}.txt", uuid::Uuid::new_v4()));
+        File::create(&path)
+            .unwrap()
+            .write_all(lines.concat().as_bytes())
+            .unwrap();
+
+        let checker = BreachChecker::open(&path).unwrap();
+        for (i, pwd) in breached.iter().enumerate() {
+            let pwd = SecretString::from(*pwd);
+            assert_eq!(checker.count(&pwd).await.unwrap(), i as u64 + 1);
+        }
+        let pwd = SecretString::from("Correct-Horse-9-Battery");
+        assert_eq!(checker.count(&pwd).await.unwrap(), 0);
+        assert_eq!(
+            checker.check(&"dragon".into()).await.unwrap(),
+            Some(Violation::Breached { count: 5 })
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -12,8 +12,22 @@ pub enum Error {
     FailSpawnBlockForValidate,
     #[error("failed spawning thread for hashing")]
     FailSpawnBlockForHash,
+    #[error("waited more than {0:?} for a free hashing slot")]
+    QueueTimeout(std::time::Duration),
+    #[error("failed spawning thread for breach check")]
+    FailSpawnBlockForBreachCheck,
     #[error("failed parsing password: {0}")]
     PwdParsingFailed(String),
     #[error("scheme error: {0}")]
     SchemeError(#[from] scheme::error::Error),
+    #[error("no pepper with id \"{0}\" exist")]
+    PepperNotFound(String),
+    #[error("invalid pepper: {0}")]
+    InvalidPepper(String),
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    #[error("failed reading breached passwords: {0}")]
+    Breach(std::io::Error),
+    #[error("failed loading configuration: {0}")]
+    Env(#[from] lerpz_utils::env::Error),
 }
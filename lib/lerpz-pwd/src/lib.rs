@@ -1,24 +1,88 @@
 //! Functions related to password hashing and verification.
 //!
-//! The schemes are:
+//! The built-in schemes are:
 //! - `01`: The default scheme, which is using Argon2 (DEFAULT).
+//! - `bcrypt`, `scrypt` and `pbkdf2`: Verification-only schemes for hashes
+//!   imported from other systems. Requires the `legacy` feature.
+//!
+//! Applications can add their own schemes with [`register_scheme`] and change
+//! which scheme new passwords are hashed with using [`set_default_scheme`].
+//! Use [`Argon2Scheme`] together with a [`Tuner`] to create a scheme with
+//! parameters that fit the machine it runs on.
+//!
+//! Passwords can be peppered with a server-side secret using [`set_peppers`].
+//! The id of the pepper is stored next to the scheme, so a hash looks like
+//! `#<scheme>:<pepper>#<hash>` when a pepper is used. The salt is generated by
+//! the scheme and is part of `<hash>`, so it doesn't need to be stored
+//! separately.
+//!
+//! Plaintext passwords are kept in a [`SecretString`], which is wiped from
+//! memory when it's dropped.
+//!
+//! Opaque tokens, like refresh tokens and API keys, are made with
+//! [`generate_token`] and stored as a keyed hash made by a [`TokenHasher`].
+//!
+//! Hashing and validation run on a [`HashingPool`], which limits how many
+//! passwords are hashed at once. Use [`set_hashing_pool`] to configure it.
+//!
+//! New passwords can be checked against a [`PasswordPolicy`] before they are
+//! hashed. With the `validator` feature, the [`Violation`]s can be turned into
+//! `validator::ValidationErrors` using [`to_validation_errors`]. Use a
+//! [`BreachChecker`] to also reject passwords found in a local dataset of
+//! breached passwords.
 
+/// Lookup of passwords in a dataset of breached passwords.
+mod breach;
 /// Errors that can occur when working with passwords.
 mod error;
 /// Parts needed for hashing and validating passwords.
 mod parts;
+/// Server-side secrets mixed into passwords.
+mod pepper;
+/// Rules that new passwords have to follow.
+mod policy;
+/// Bounded pool of threads used for hashing.
+mod pool;
 /// Schemas for hashing and validating passwords.
 mod scheme;
+/// Strings that are wiped from memory when dropped.
+mod secret;
+/// Random tokens and the hashes they are stored as.
+mod token;
+/// Tuning of Argon2 parameters.
+mod tune;
+/// Outcome of verifying a password.
+mod verdict;
 
-use std::{str::FromStr, sync::Arc};
+use std::str::FromStr;
 
 use rand::Rng;
 
+pub use argon2;
+pub use breach::BreachChecker;
 pub use error::{Error, Result};
 pub use parts::{HashParts, PwdParts};
-pub use scheme::{Scheme, get_scheme};
+pub use pepper::{Peppers, peppers, set_peppers};
+#[cfg(feature = "validator")]
+pub use policy::to_validation_errors;
+pub use policy::{PasswordPolicy, Violation, strength_score};
+pub use pool::{HashingPool, PoolMetrics, hashing_pool, set_hashing_pool};
+pub use scheme::{
+    Argon2Scheme, Scheme, SchemeRegistry, default_scheme, get_scheme, register_scheme,
+    set_default_scheme,
+};
+#[cfg(feature = "legacy")]
+pub use scheme::{BcryptScheme, Pbkdf2Scheme, ScryptScheme};
+pub use secret::SecretString;
+pub use token::{TokenHasher, check_token, generate_token, token_prefix};
+pub use tune::Tuner;
+pub use verdict::Verdict;
 
 /// Default scheme used for hashing passwords.
+///
+/// This is the scheme the registry starts out with. It can be changed at
+/// runtime using [`set_default_scheme`], which is why [`default_scheme`]
+/// should be used to find the scheme currently in use.
 pub static DEFAULT_SCHEME: &str = "01";
 
 /// Hash a password using the latest scheme.
@@ -26,8 +90,10 @@ pub static DEFAULT_SCHEME: &str = "01";
 /// You can use [`PwdParts::new`] which means it will always use the latest
 /// scheme. If you want to use an old scheme, you can use [`hash_pwd_parts`]
 /// with a [`PwdParts`] that has the scheme set to the desired value.
-pub async fn hash_pwd(pwd: &str, salt: &str) -> Result<String> {
-    hash_pwd_parts(PwdParts::new(pwd.into(), salt.into())).await
+///
+/// The salt is generated by the scheme and stored in the returned hash.
+pub async fn hash_pwd(pwd: impl Into<SecretString>) -> Result<String> {
+    hash_pwd_parts(PwdParts::new(pwd)).await
 }
 
 /// Hash a password using custom [`PwdParts`].
@@ -38,34 +104,39 @@ pub async fn hash_pwd(pwd: &str, salt: &str) -> Result<String> {
 /// You can use this function together with the [`PwdParts::new`] method to
 /// create a password using the latest scheme.
 pub async fn hash_pwd_parts(pwd_parts: PwdParts) -> Result<String> {
-    tokio::task::spawn_blocking(move || {
-        get_scheme(&pwd_parts.scheme)?
-            .hash(&pwd_parts.pwd, &pwd_parts.salt)
-            .map(|hash| format!("#{}#{}", pwd_parts.scheme, hash))
-            .map_err(Error::SchemeError)
-    })
-    .await
-    .map_err(|_| Error::FailSpawnBlockForHash)
-    .and_then(|res| res)
+    let peppers = peppers();
+    hashing_pool()
+        .run(
+            move || {
+                let pwd = peppers.apply(pwd_parts.pepper.as_deref(), &pwd_parts.pwd)?;
+                let hash = get_scheme(&pwd_parts.scheme)?.hash(pwd.expose_secret())?;
+                Ok(HashParts {
+                    scheme: pwd_parts.scheme,
+                    pepper: pwd_parts.pepper,
+                    hash,
+                }
+                .to_string())
+            },
+            Error::FailSpawnBlockForHash,
+        )
+        .await
 }
 
-/// Validate a password hash against a password and a salt.
+/// Validate a password hash against a password.
 ///
 /// The hash needs to be parseable into [`HashParts`]. Checkout
 /// [`HashParts::from_str`] for how the format works.
-pub async fn validate_pwd(pwd_hash: &str, pwd_salt: &str, pwd_ref: &str) -> Result<bool> {
+pub async fn validate_pwd(pwd_hash: &str, pwd_ref: impl Into<SecretString>) -> Result<bool> {
     let pwd_hash = HashParts::from_str(pwd_hash)?;
-    let pwd_salt = Arc::from(pwd_salt);
-    let pwd_ref = Arc::from(pwd_ref);
-    validate_pwd_parts(pwd_hash, pwd_salt, pwd_ref).await
+    validate_pwd_parts(pwd_hash, pwd_ref.into()).await
 }
 
-/// Validate a password using [`HashParts`] against a password and a salt.
+/// Validate a password using [`HashParts`] against a password.
 ///
-/// This function validates a password hash against a password and salt. This
-/// uses the [`HashParts`] to decide which scheme to use for validating. You can
-/// use the [`HashParts::from_str`] to create the [`HashParts`] needed for
-/// validating the password scheme. If you do not use the correct scheme for the
+/// This function validates a password hash against a password. This uses the
+/// [`HashParts`] to decide which scheme to use for validating. You can use the
+/// [`HashParts::from_str`] to create the [`HashParts`] needed for validating
+/// the password scheme. If you do not use the correct scheme for the
 /// password, this function will error.
 ///
 /// # Note
@@ -75,18 +146,83 @@ pub async fn validate_pwd(pwd_hash: &str, pwd_salt: &str, pwd_ref: &str) -> Resu
 /// hash.
 pub async fn validate_pwd_parts(
     parts: impl Into<HashParts>,
-    pwd_salt: Arc<str>,
-    pwd_ref: Arc<str>,
+    pwd_ref: SecretString,
 ) -> Result<bool> {
     let parts: HashParts = parts.into();
-    tokio::task::spawn_blocking(move || {
-        get_scheme(&parts.scheme)?
-            .validate(&parts.hash, &pwd_ref, &pwd_salt)
-            .map_err(Error::SchemeError)
-    })
-    .await
-    .map_err(|_| Error::FailSpawnBlockForValidate)
-    .and_then(|res| res)
+    let peppers = peppers();
+    hashing_pool()
+        .run(
+            move || {
+                let pwd_ref = peppers.apply(parts.pepper.as_deref(), &pwd_ref)?;
+                get_scheme(&parts.scheme)?
+                    .validate(&parts.hash, pwd_ref.expose_secret())
+                    .map_err(Error::SchemeError)
+            },
+            Error::FailSpawnBlockForValidate,
+        )
+        .await
+}
+
+/// Verify a password hash against a password.
+///
+/// This works like [`validate_pwd`], but returns a [`Verdict`] that tells if
+/// the hash was made with an outdated scheme. When it was, the verdict will
+/// contain a new hash made with the default scheme, which should be stored
+/// in place of the old one.
+pub async fn verify_pwd(pwd_hash: &str, pwd_ref: impl Into<SecretString>) -> Result<Verdict> {
+    let pwd_hash = HashParts::from_str(pwd_hash)?;
+    verify_pwd_parts(pwd_hash, pwd_ref.into()).await
+}
+
+/// Verify a password using [`HashParts`] against a password.
+///
+/// See [`verify_pwd`] and [`validate_pwd_parts`] for more information.
+///
+/// A hash needs to be rehashed when its scheme is not the default scheme, when
+/// it doesn't use the active pepper, or when the scheme itself reports that
+/// the hash is outdated through [`Scheme::needs_rehash`].
+pub async fn verify_pwd_parts(
+    parts: impl Into<HashParts>,
+    pwd_ref: SecretString,
+) -> Result<Verdict> {
+    let parts: HashParts = parts.into();
+    let peppers = peppers();
+    hashing_pool()
+        .run(
+            move || {
+                let scheme = get_scheme(&parts.scheme)?;
+                let peppered = peppers.apply(parts.pepper.as_deref(), &pwd_ref)?;
+                if !scheme.validate(&parts.hash, peppered.expose_secret())? {
+                    return Ok(Verdict::Invalid);
+                }
+
+                let default = default_scheme();
+                let active_pepper = peppers.active();
+                if parts.scheme == default
+                    && parts.pepper.as_deref() == active_pepper
+                    && !scheme.needs_rehash(&parts.hash)
+                {
+                    return Ok(Verdict::Valid {
+                        needs_rehash: false,
+                        new_hash: None,
+                    });
+                }
+
+                let peppered = peppers.apply(active_pepper, &pwd_ref)?;
+                let new_hash = HashParts {
+                    hash: get_scheme(&default)?.hash(peppered.expose_secret())?,
+                    scheme: default,
+                    pepper: active_pepper.map(String::from),
+                };
+
+                Ok(Verdict::Valid {
+                    needs_rehash: true,
+                    new_hash: Some(new_hash.to_string()),
+                })
+            },
+            Error::FailSpawnBlockForValidate,
+        )
+        .await
 }
 
 /// Generate a random salt for password hashing.
@@ -94,6 +230,7 @@ pub async fn validate_pwd_parts(
 /// This function generates a random string that can be used as a salt for
 /// password hashing. The salt is 16 bytes long and is generated using the
 /// [`rand`] crate and encoded using [`hex`].
+#[deprecated(note = "schemes generate their own salt and store it in the hash")]
 pub fn generate_salt_hex() -> String {
     let mut rng = rand::rng();
     let mut salt = [0u8; 16];
@@ -103,16 +240,64 @@ pub fn generate_salt_hex() -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{hash_pwd, validate_pwd};
+    use super::{Peppers, Verdict, hash_pwd, set_peppers, validate_pwd, verify_pwd};
+
+    /// Loads the test environment and the peppers defined in it.
+    fn setup() {
+        dotenvy::from_filename(".env.test").unwrap();
+        set_peppers(Peppers::from_env().unwrap());
+    }
 
     #[tokio::test]
     async fn test_password_hashing_and_validate() {
-        dotenvy::from_filename(".env.test").unwrap();
+        setup();
+
+        let hash = hash_pwd("password").await.unwrap();
+
+        assert!(!validate_pwd(&hash, "drowssap").await.unwrap());
+        assert!(validate_pwd(&hash, "password").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_password_unique_salt() {
+        setup();
+
+        let first = hash_pwd("password").await.unwrap();
+        let second = hash_pwd("password").await.unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_password_verify() {
+        setup();
+
+        let hash = hash_pwd("password").await.unwrap();
+
+        let verdict = verify_pwd(&hash, "drowssap").await.unwrap();
+        assert_eq!(verdict, Verdict::Invalid);
+
+        let verdict = verify_pwd(&hash, "password").await.unwrap();
+        assert_eq!(
+            verdict,
+            Verdict::Valid {
+                needs_rehash: false,
+                new_hash: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_password_pepper() {
+        setup();
+
+        let hash = hash_pwd("password").await.unwrap();
+        assert!(hash.starts_with("#01:test#"));
 
-        let salt = uuid::Uuid::new_v4().to_string();
-        let hash = hash_pwd("password", &salt).await.unwrap();
+        assert!(!validate_pwd(&hash, "drowssap").await.unwrap());
+        assert!(validate_pwd(&hash, "password").await.unwrap());
 
-        assert!(!validate_pwd(&hash, &salt, "drowssap").await.unwrap());
-        assert!(validate_pwd(&hash, &salt, "password").await.unwrap());
+        let unpeppered = hash.replacen("#01:test#", "#01#", 1);
+        assert!(!validate_pwd(&unpeppered, "password").await.unwrap());
     }
 }
@@ -1,43 +1,74 @@
 use regex::Regex;
-use std::{str::FromStr, sync::LazyLock};
+use std::{fmt, str::FromStr, sync::LazyLock};
 
-use super::{DEFAULT_SCHEME, error::Error};
+use super::{error::Error, pepper::peppers, scheme::default_scheme, secret::SecretString};
 
 /// A regex that turns a password hash into its parts.
 static PWD_PARTS_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^#(?<scheme>\w+)#(?<hash>.+)$").unwrap());
+    LazyLock::new(|| Regex::new(r"^#(?<scheme>\w+)(?::(?<pepper>\w+))?#(?<hash>.+)$").unwrap());
 
 /// All parts a password needs to be hashed.
+///
+/// There is no salt, since each scheme generates its own salt and stores it
+/// as part of the hash.
+#[derive(Debug)]
 pub struct PwdParts {
     pub scheme: String,
-    pub salt: String,
-    pub pwd: String,
+    pub pepper: Option<String>,
+    pub pwd: SecretString,
 }
 
 /// What passwords gets turned into when hashed.
 #[derive(Debug, Clone)]
 pub struct HashParts {
     pub scheme: String,
+    pub pepper: Option<String>,
     pub hash: String,
 }
 
 impl PwdParts {
     /// Creates a new [`PwdParts`] structure.
     ///
-    /// This will have the latest scheme for hashing.
-    pub fn new(pwd: String, salt: String) -> Self {
+    /// This will have the latest scheme for hashing, which is the default
+    /// scheme of the registry, and the active pepper.
+    pub fn new(pwd: impl Into<SecretString>) -> Self {
         Self {
-            scheme: DEFAULT_SCHEME.into(),
-            salt,
-            pwd,
+            scheme: default_scheme(),
+            pepper: peppers().active().map(String::from),
+            pwd: pwd.into(),
         }
     }
 }
 
 impl HashParts {
     /// Creates a new [`HashParts`] structure.
+    ///
+    /// The hash will not use a pepper. Use [`HashParts::with_pepper`] to set
+    /// the pepper the hash was made with.
     pub fn new(scheme: String, hash: String) -> Self {
-        Self { scheme, hash }
+        Self {
+            scheme,
+            pepper: None,
+            hash,
+        }
+    }
+
+    /// Sets the id of the pepper used for the hash.
+    pub fn with_pepper(mut self, pepper: impl Into<String>) -> Self {
+        self.pepper = Some(pepper.into());
+        self
+    }
+}
+
+impl fmt::Display for HashParts {
+    /// Formats the parts as `#<scheme>#<hash>` or `#<scheme>:<pepper>#<hash>`
+    /// if a pepper is used. This is the format parsed by
+    /// [`HashParts::from_str`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pepper {
+            Some(pepper) => write!(f, "#{}:{}#{}", self.scheme, pepper, self.hash),
+            None => write!(f, "#{}#{}", self.scheme, self.hash),
+        }
     }
 }
 
@@ -61,7 +92,12 @@ impl FromStr for HashParts {
 
         let scheme = get_group("scheme")?;
         let hash = get_group("hash")?;
+        let pepper = captures.name("pepper").map(|m| m.as_str().to_string());
 
-        Ok(HashParts::new(scheme, hash))
+        Ok(HashParts {
+            scheme,
+            pepper,
+            hash,
+        })
     }
 }
@@ -0,0 +1,176 @@
+//! Server-side secrets mixed into passwords before they are hashed.
+//!
+//! A pepper is a secret that is not stored in the database, so a dump of the
+//! database alone is not enough to crack the hashes offline. The password is
+//! run through HMAC-SHA256 keyed with the pepper before it is given to the
+//! scheme.
+//!
+//! Every pepper has an id, which is stored in the envelope of the hash (e.g.
+//! `#01:k1#...`). This makes it possible to rotate peppers: new hashes use the
+//! active pepper, while old hashes keep using the pepper they were made with
+//! until they are rehashed. A pepper can be retired by removing it, after
+//! which hashes using it can no longer be validated.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, LazyLock, PoisonError, RwLock},
+};
+
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::{
+    SecretString,
+    error::{Error, Result},
+};
+
+/// A regex that matches valid pepper ids.
+static PEPPER_ID_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\w+$").unwrap());
+
+/// The peppers used by the hashing and validation functions.
+static PEPPERS: LazyLock<RwLock<Arc<Peppers>>> = LazyLock::new(Default::default);
+
+/// A set of peppers keyed by their id.
+///
+/// The secrets are wiped from memory when the set is dropped.
+#[derive(Clone, Default)]
+pub struct Peppers {
+    secrets: HashMap<String, Zeroizing<Vec<u8>>>,
+    active: Option<String>,
+}
+
+impl Peppers {
+    /// Creates an empty set of peppers.
+    ///
+    /// Passwords are not peppered as long as no pepper is active.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the peppers from environment variables.
+    ///
+    /// - `PWD_PEPPERS`: Comma separated list of `<id>:<hex secret>` pairs.
+    /// - `PWD_PEPPER_ID`: The id of the pepper used for new hashes.
+    ///
+    /// At least one pepper has to be given.
+    pub fn from_env() -> Result<Self> {
+        let mut peppers: Peppers = lerpz_utils::get_env("PWD_PEPPERS")?.parse()?;
+        if peppers.secrets.is_empty() {
+            return Err(Error::InvalidPepper("PWD_PEPPERS is empty".into()));
+        }
+        peppers.set_active(&lerpz_utils::get_env("PWD_PEPPER_ID")?)?;
+        Ok(peppers)
+    }
+
+    /// Adds a pepper with the given id.
+    ///
+    /// Returns an error if the id is not valid or already in use.
+    pub fn insert(&mut self, id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Result<()> {
+        let id = id.into();
+        let secret = Zeroizing::new(secret.into());
+
+        if !PEPPER_ID_REGEX.is_match(&id) {
+            return Err(Error::InvalidPepper(format!("\"{id}\" is not a valid id")));
+        }
+        if secret.is_empty() {
+            return Err(Error::InvalidPepper(format!(
+                "\"{id}\" has an empty secret"
+            )));
+        }
+        if self.secrets.contains_key(&id) {
+            return Err(Error::InvalidPepper(format!(
+                "\"{id}\" is used more than once"
+            )));
+        }
+
+        self.secrets.insert(id, secret);
+        Ok(())
+    }
+
+    /// Sets which pepper is used for new hashes.
+    pub fn set_active(&mut self, id: &str) -> Result<()> {
+        if !self.secrets.contains_key(id) {
+            return Err(Error::PepperNotFound(id.into()));
+        }
+
+        self.active = Some(id.into());
+        Ok(())
+    }
+
+    /// Returns the id of the pepper used for new hashes.
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Mixes the pepper with the given id into a password.
+    ///
+    /// Returns the password unchanged if `id` is [`None`].
+    pub fn apply(&self, id: Option<&str>, pwd: &SecretString) -> Result<SecretString> {
+        let Some(id) = id else {
+            return Ok(pwd.clone());
+        };
+
+        let secret = self
+            .secrets
+            .get(id)
+            .ok_or_else(|| Error::PepperNotFound(id.into()))?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+            .map_err(|err| Error::InvalidPepper(err.to_string()))?;
+        mac.update(pwd.expose_secret().as_bytes());
+
+        Ok(hex::encode(mac.finalize().into_bytes()).into())
+    }
+}
+
+impl FromStr for Peppers {
+    type Err = Error;
+
+    /// Parses a comma separated list of `<id>:<hex secret>` pairs.
+    ///
+    /// The parsed set has no active pepper.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut peppers = Peppers::new();
+
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (id, secret) = pair
+                .split_once(':')
+                .ok_or_else(|| Error::InvalidPepper("expected \"<id>:<secret>\"".into()))?;
+            let secret = hex::decode(secret)
+                .map_err(|_| Error::InvalidPepper(format!("\"{id}\" is not hex encoded")))?;
+            peppers.insert(id, secret)?;
+        }
+
+        Ok(peppers)
+    }
+}
+
+impl fmt::Debug for Peppers {
+    /// Only shows the ids, so that secrets don't end up in logs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Peppers")
+            .field("ids", &self.secrets.keys().collect::<Vec<_>>())
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+/// Replaces the peppers used for hashing and validating passwords.
+///
+/// This should be done once when the application starts, before any password
+/// is hashed or validated.
+pub fn set_peppers(peppers: Peppers) {
+    *PEPPERS.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(peppers);
+}
+
+/// Returns the peppers used for hashing and validating passwords.
+pub fn peppers() -> Arc<Peppers> {
+    PEPPERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
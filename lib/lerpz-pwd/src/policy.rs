@@ -0,0 +1,493 @@
+//! Password policies and the violations they report.
+
+use std::fmt;
+
+/// Rules a password has to follow.
+///
+/// The policy is checked using [`PasswordPolicy::check`], which returns every
+/// rule the password breaks, so that all of them can be shown to the user at
+/// once.
+///
+/// ### Example
+///
+/// ```rust
+/// use lerpz_pwd::{PasswordPolicy, Violation};
+///
+/// let policy = PasswordPolicy::default().with_min_length(8);
+/// let violations = policy.check("kasper123", &["kasper"]);
+///
+/// assert!(violations.contains(&Violation::ContainsBanned));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    require_lowercase: bool,
+    require_uppercase: bool,
+    require_digit: bool,
+    require_symbol: bool,
+    max_repeated: Option<usize>,
+    min_score: Option<u8>,
+}
+
+/// A rule of a [`PasswordPolicy`] that a password breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The password has fewer characters than allowed.
+    TooShort { min: usize },
+    /// The password has more characters than allowed.
+    TooLong { max: usize },
+    /// The password has no lowercase letters.
+    MissingLowercase,
+    /// The password has no uppercase letters.
+    MissingUppercase,
+    /// The password has no digits.
+    MissingDigit,
+    /// The password has no symbols.
+    MissingSymbol,
+    /// The password contains something that is not allowed, like the
+    /// username or email of the user.
+    ContainsBanned,
+    /// The password repeats the same character too many times in a row.
+    RepeatedCharacters { max: usize },
+    /// The password is too easy to guess.
+    TooWeak { score: u8, min: u8 },
+    /// The password has appeared in a data breach.
+    Breached { count: u64 },
+}
+
+impl PasswordPolicy {
+    /// Creates a policy with no rules except a length between 1 and
+    /// [`usize::MAX`] characters.
+    pub fn new() -> Self {
+        Self {
+            min_length: 1,
+            max_length: usize::MAX,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            max_repeated: None,
+            min_score: None,
+        }
+    }
+
+    /// Sets the least amount of characters a password must have.
+    pub fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    /// Sets the most characters a password can have.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Requires at least one lowercase letter.
+    pub fn require_lowercase(mut self) -> Self {
+        self.require_lowercase = true;
+        self
+    }
+
+    /// Requires at least one uppercase letter.
+    pub fn require_uppercase(mut self) -> Self {
+        self.require_uppercase = true;
+        self
+    }
+
+    /// Requires at least one digit.
+    pub fn require_digit(mut self) -> Self {
+        self.require_digit = true;
+        self
+    }
+
+    /// Requires at least one character that is not a letter or a digit.
+    pub fn require_symbol(mut self) -> Self {
+        self.require_symbol = true;
+        self
+    }
+
+    /// Sets how many times the same character can be repeated in a row.
+    pub fn with_max_repeated(mut self, max_repeated: usize) -> Self {
+        self.max_repeated = Some(max_repeated);
+        self
+    }
+
+    /// Sets the lowest [`strength_score`] a password must have.
+    pub fn with_min_score(mut self, min_score: u8) -> Self {
+        self.min_score = Some(min_score.min(4));
+        self
+    }
+
+    /// Checks a password against the policy.
+    ///
+    /// The `banned` values are things the password must not contain, like the
+    /// username or email of the user. They are compared case-insensitively
+    /// and only as whole words, so `kas` bans `kas2024!` but not `kasper`.
+    /// Values shorter than 3 characters are ignored, and for emails the part
+    /// before the `@` is checked as well.
+    ///
+    /// Returns all violations, which is empty if the password is allowed.
+    pub fn check(&self, pwd: &str, banned: &[&str]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let length = pwd.chars().count();
+
+        if length < self.min_length {
+            violations.push(Violation::TooShort {
+                min: self.min_length,
+            });
+        }
+        if length > self.max_length {
+            violations.push(Violation::TooLong {
+                max: self.max_length,
+            });
+        }
+
+        let has = |f: fn(&char) -> bool| pwd.chars().any(|c| f(&c));
+        if self.require_lowercase && !has(|c| c.is_lowercase()) {
+            violations.push(Violation::MissingLowercase);
+        }
+        if self.require_uppercase && !has(|c| c.is_uppercase()) {
+            violations.push(Violation::MissingUppercase);
+        }
+        if self.require_digit && !has(|c| c.is_numeric()) {
+            violations.push(Violation::MissingDigit);
+        }
+        if self.require_symbol && !has(|c| !c.is_alphanumeric()) {
+            violations.push(Violation::MissingSymbol);
+        }
+
+        if contains_banned(pwd, banned) {
+            violations.push(Violation::ContainsBanned);
+        }
+
+        if let Some(max) = self.max_repeated
+            && longest_repeat(pwd) > max
+        {
+            violations.push(Violation::RepeatedCharacters { max });
+        }
+
+        if let Some(min) = self.min_score {
+            let score = strength_score(pwd, banned);
+            if score < min {
+                violations.push(Violation::TooWeak { score, min });
+            }
+        }
+
+        violations
+    }
+}
+
+impl Default for PasswordPolicy {
+    /// A policy following the NIST SP 800-63B guidelines.
+    ///
+    /// Passwords must be between 12 and 128 characters, not contain banned
+    /// values, not repeat a character more than 3 times in a row and have a
+    /// [`strength_score`] of at least 3. No character classes are required.
+    fn default() -> Self {
+        Self::new()
+            .with_min_length(12)
+            .with_max_length(128)
+            .with_max_repeated(3)
+            .with_min_score(3)
+    }
+}
+
+impl Violation {
+    /// A stable identifier for the violation.
+    ///
+    /// This can be used by clients to show their own messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Violation::TooShort { .. } => "too_short",
+            Violation::TooLong { .. } => "too_long",
+            Violation::MissingLowercase => "missing_lowercase",
+            Violation::MissingUppercase => "missing_uppercase",
+            Violation::MissingDigit => "missing_digit",
+            Violation::MissingSymbol => "missing_symbol",
+            Violation::ContainsBanned => "contains_banned",
+            Violation::RepeatedCharacters { .. } => "repeated_characters",
+            Violation::TooWeak { .. } => "too_weak",
+            Violation::Breached { .. } => "breached",
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    /// A human-readable message describing the violation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::TooShort { min } => {
+                write!(f, "Password must be at least {min} characters long.")
+            }
+            Violation::TooLong { max } => {
+                write!(f, "Password must be at most {max} characters long.")
+            }
+            Violation::MissingLowercase => write!(f, "Password must contain a lowercase letter."),
+            Violation::MissingUppercase => write!(f, "Password must contain an uppercase letter."),
+            Violation::MissingDigit => write!(f, "Password must contain a digit."),
+            Violation::MissingSymbol => write!(f, "Password must contain a symbol."),
+            Violation::ContainsBanned => {
+                write!(f, "Password must not contain your username or email.")
+            }
+            Violation::RepeatedCharacters { max } => write!(
+                f,
+                "Password must not repeat a character more than {max} times in a row."
+            ),
+            Violation::TooWeak { .. } => write!(f, "Password is too easy to guess."),
+            Violation::Breached { .. } => {
+                write!(f, "Password has appeared in a data breach.")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "validator")]
+impl From<&Violation> for validator::ValidationError {
+    fn from(violation: &Violation) -> Self {
+        let mut error = validator::ValidationError::new(violation.code())
+            .with_message(violation.to_string().into());
+
+        match violation {
+            Violation::TooShort { min } => error.add_param("min".into(), min),
+            Violation::TooLong { max } | Violation::RepeatedCharacters { max } => {
+                error.add_param("max".into(), max)
+            }
+            Violation::TooWeak { score, min } => {
+                error.add_param("score".into(), score);
+                error.add_param("min".into(), min);
+            }
+            Violation::Breached { count } => error.add_param("count".into(), count),
+            _ => {}
+        }
+
+        error
+    }
+}
+
+/// Turns violations into [`validator::ValidationErrors`] for the given field.
+///
+/// This makes it possible to return the violations the same way as any other
+/// validation error, e.g. using the `ValidationErrorResponse` of `lerpz-axum`.
+#[cfg(feature = "validator")]
+pub fn to_validation_errors(
+    field: &'static str,
+    violations: &[Violation],
+) -> validator::ValidationErrors {
+    let mut errors = validator::ValidationErrors::new();
+    for violation in violations {
+        errors.add(field, violation.into());
+    }
+    errors
+}
+
+/// Estimates how hard a password is to guess on a scale from 0 to 4.
+///
+/// The scale follows the one used by zxcvbn, where each step roughly means:
+/// - `0`: Less than 10^3 guesses.
+/// - `1`: Less than 10^6 guesses.
+/// - `2`: Less than 10^8 guesses.
+/// - `3`: Less than 10^10 guesses.
+/// - `4`: 10^10 guesses or more.
+///
+/// Every character adds guesses based on the size of its character class,
+/// except that repeated characters and sequences (`aaa`, `abc`, `321`) add
+/// far less, and common passwords and banned values count as a single guess
+/// from a small dictionary.
+pub fn strength_score(pwd: &str, banned: &[&str]) -> u8 {
+    if COMMON_PASSWORDS.contains(&pwd.to_lowercase().as_str()) {
+        return 0;
+    }
+
+    let chars: Vec<char> = pwd.chars().collect();
+    let lowered: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let mut weights: Vec<f64> = lowered
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let class = match chars[i] {
+                c if c.is_ascii_lowercase() || c.is_ascii_uppercase() => 26.0,
+                c if c.is_ascii_digit() => 10.0,
+                c if c.is_ascii() => 33.0,
+                _ => 100.0,
+            };
+            let pattern = i > 0 && (*c as i64 - lowered[i - 1] as i64).abs() <= 1;
+            f64::log10(class) * if pattern { 0.25 } else { 1.0 }
+        })
+        .collect();
+
+    let words: Vec<String> = banned_values(banned)
+        .chain(COMMON_PASSWORDS.iter().map(|word| word.to_string()))
+        .collect();
+    let dictionary = (words.len() as f64).log10();
+    for word in words {
+        let word: Vec<char> = word.chars().collect();
+        if word.len() < 4 {
+            continue;
+        }
+        for start in 0..lowered.len().saturating_sub(word.len() - 1) {
+            if lowered[start..start + word.len()] == word[..] {
+                weights[start..start + word.len()].fill(0.0);
+                weights[start] = dictionary;
+            }
+        }
+    }
+
+    match weights.iter().sum::<f64>() {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    }
+}
+
+/// Passwords that are so common that they are guessed right away.
+static COMMON_PASSWORDS: &[&str] = &[
+    "password",
+    "passw0rd",
+    "123456",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "qwerty",
+    "qwertyuiop",
+    "asdfgh",
+    "zxcvbn",
+    "abc123",
+    "letmein",
+    "welcome",
+    "admin",
+    "iloveyou",
+    "monkey",
+    "dragon",
+    "football",
+    "baseball",
+    "sunshine",
+    "princess",
+    "master",
+    "shadow",
+    "superman",
+    "trustno1",
+    "lerpz",
+    "summer",
+    "winter",
+    "spring",
+    "autumn",
+];
+
+/// Returns the lowercased banned values, including the local part of emails.
+fn banned_values<'a>(banned: &'a [&'a str]) -> impl Iterator<Item = String> + 'a {
+    banned
+        .iter()
+        .flat_map(|value| {
+            let local = value.split_once('@').map(|(local, _)| local);
+            std::iter::once(*value).chain(local)
+        })
+        .map(str::to_lowercase)
+        .filter(|value| value.chars().count() >= 3)
+}
+
+/// Whether the password contains any of the banned values as a whole word.
+///
+/// A value counts as a word when it isn't directly preceded or followed by a
+/// letter in the password.
+fn contains_banned(pwd: &str, banned: &[&str]) -> bool {
+    let pwd = pwd.to_lowercase();
+    let is_letter = |c: Option<char>| c.is_some_and(char::is_alphabetic);
+
+    banned_values(banned).any(|value| {
+        pwd.match_indices(&value).any(|(start, _)| {
+            let before = pwd[..start].chars().next_back();
+            let after = pwd[start + value.len()..].chars().next();
+            !is_letter(before) && !is_letter(after)
+        })
+    })
+}
+
+/// Returns the length of the longest run of the same character.
+fn longest_repeat(pwd: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut last = None;
+
+    for c in pwd.chars() {
+        current = if Some(c) == last { current + 1 } else { 1 };
+        longest = longest.max(current);
+        last = Some(c);
+    }
+
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_violations() {
+        let policy = PasswordPolicy::new()
+            .with_min_length(12)
+            .require_uppercase()
+            .require_digit()
+            .with_max_repeated(2);
+
+        let violations = policy.check("kas!!!", &["kas@lerpz.com"]);
+        assert_eq!(
+            violations,
+            vec![
+                Violation::TooShort { min: 12 },
+                Violation::MissingUppercase,
+                Violation::MissingDigit,
+                Violation::ContainsBanned,
+                Violation::RepeatedCharacters { max: 2 },
+            ]
+        );
+
+        assert!(
+            policy
+                .check("Correct-Horse-9-Battery", &["kasper"])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_contains_banned() {
+        assert!(contains_banned("Kasper2024!", &["kasper"]));
+        assert!(contains_banned("my-kasper-pwd", &["kasper@lerpz.com"]));
+        assert!(!contains_banned("kasperrr", &["kas"]));
+        assert!(!contains_banned("grandmaster", &["ma"]));
+        assert!(!contains_banned("correct horse", &[]));
+    }
+
+    #[cfg(feature = "validator")]
+    #[test]
+    fn test_to_validation_errors() {
+        let violations = [Violation::TooShort { min: 12 }, Violation::MissingDigit];
+        let errors = to_validation_errors("password", &violations);
+
+        let errors = &errors.field_errors()["password"];
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].code, "too_short");
+        assert_eq!(errors[0].params["min"], 12);
+        assert_eq!(
+            errors[0].message.as_deref(),
+            Some("Password must be at least 12 characters long.")
+        );
+        assert_eq!(errors[1].code, "missing_digit");
+        assert!(errors[1].params.is_empty());
+    }
+
+    #[test]
+    fn test_strength_score() {
+        assert_eq!(strength_score("password", &[]), 0);
+        assert!(strength_score("abcdefgh", &[]) < 2);
+        assert!(strength_score("kasper2020!", &["kasper"]) < 3);
+        assert_eq!(strength_score("Correct-Horse-9-Battery", &[]), 4);
+    }
+}
@@ -0,0 +1,283 @@
+//! Limiting how many passwords are hashed at the same time.
+//!
+//! Every hash allocates the memory cost of its scheme (19 MiB for scheme
+//! `01`), so hashing an unbounded amount of passwords at once can run the
+//! process out of memory. All hashing and validation therefore goes through a
+//! [`HashingPool`], which only lets a fixed amount of jobs run at once. Jobs
+//! that can't start within the queue timeout fail with
+//! [`Error::QueueTimeout`], so callers can shed load instead of piling up.
+
+use std::{
+    sync::{
+        Arc, LazyLock, PoisonError, RwLock,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Semaphore;
+
+use crate::error::{Error, Result};
+
+/// The pool used by the hashing and validation functions.
+static POOL: LazyLock<RwLock<Arc<HashingPool>>> = LazyLock::new(Default::default);
+
+/// A bounded pool of blocking threads used for hashing passwords.
+#[derive(Debug)]
+pub struct HashingPool {
+    permits: Arc<Semaphore>,
+    max_concurrency: usize,
+    queue_timeout: Duration,
+    counters: Arc<Counters>,
+}
+
+/// A snapshot of the counters of a [`HashingPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Jobs waiting for a free slot right now.
+    pub queued: usize,
+    /// Jobs running right now.
+    pub running: usize,
+    /// Jobs that have finished since the pool was created.
+    pub completed: u64,
+    /// Jobs that gave up waiting for a free slot.
+    pub timed_out: u64,
+    /// Total time jobs have spent waiting for a free slot.
+    pub total_wait: Duration,
+    /// Total time jobs have spent running.
+    pub total_latency: Duration,
+}
+
+/// The counters that are updated by the jobs of a pool.
+#[derive(Debug, Default)]
+struct Counters {
+    queued: AtomicUsize,
+    running: AtomicUsize,
+    completed: AtomicU64,
+    timed_out: AtomicU64,
+    wait_micros: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+impl HashingPool {
+    /// Creates a pool that runs at most `max_concurrency` jobs at once, and
+    /// lets jobs wait at most `queue_timeout` for a free slot.
+    pub fn new(max_concurrency: usize, queue_timeout: Duration) -> Self {
+        let max_concurrency = max_concurrency.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrency)),
+            max_concurrency,
+            queue_timeout,
+            counters: Default::default(),
+        }
+    }
+
+    /// Creates a pool from environment variables.
+    ///
+    /// - `PWD_MAX_CONCURRENCY`: The most jobs that run at once. Defaults to
+    ///   the number of CPUs.
+    /// - `PWD_QUEUE_TIMEOUT_MS`: How long a job may wait for a free slot in
+    ///   milliseconds. Defaults to 5000.
+    pub fn from_env() -> Result<Self> {
+        let default = Self::default();
+        let max_concurrency = get_env_or("PWD_MAX_CONCURRENCY", default.max_concurrency)?;
+        let queue_timeout = get_env_or("PWD_QUEUE_TIMEOUT_MS", 5000)?;
+        Ok(Self::new(
+            max_concurrency,
+            Duration::from_millis(queue_timeout),
+        ))
+    }
+
+    /// The most jobs that run at once.
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    /// How long a job may wait for a free slot.
+    pub fn queue_timeout(&self) -> Duration {
+        self.queue_timeout
+    }
+
+    /// Returns a snapshot of the counters of the pool.
+    pub fn metrics(&self) -> PoolMetrics {
+        let counters = &self.counters;
+        PoolMetrics {
+            queued: counters.queued.load(Ordering::Relaxed),
+            running: counters.running.load(Ordering::Relaxed),
+            completed: counters.completed.load(Ordering::Relaxed),
+            timed_out: counters.timed_out.load(Ordering::Relaxed),
+            total_wait: Duration::from_micros(counters.wait_micros.load(Ordering::Relaxed)),
+            total_latency: Duration::from_micros(counters.latency_micros.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Runs a job on a blocking thread once there is a free slot.
+    ///
+    /// The slot is held until the job is done, even if the returned future is
+    /// dropped before that. `spawn_error` is returned if the job panics.
+    pub(crate) async fn run<T, F>(&self, job: F, spawn_error: Error) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let counters = self.counters.clone();
+        let queued_at = Instant::now();
+
+        let queued = Counted::new(&counters.queued);
+        let permit =
+            tokio::time::timeout(self.queue_timeout, self.permits.clone().acquire_owned()).await;
+        drop(queued);
+
+        let Ok(Ok(permit)) = permit else {
+            counters.timed_out.fetch_add(1, Ordering::Relaxed);
+            return Err(Error::QueueTimeout(self.queue_timeout));
+        };
+        add_micros(&counters.wait_micros, queued_at.elapsed());
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let started_at = Instant::now();
+            let running = Counted::new(&counters.running);
+
+            let result = job();
+
+            drop(running);
+            counters.completed.fetch_add(1, Ordering::Relaxed);
+            add_micros(&counters.latency_micros, started_at.elapsed());
+            result
+        })
+        .await
+        .map_err(|_| spawn_error)
+        .and_then(|res| res)
+    }
+}
+
+impl Default for HashingPool {
+    /// A pool with one slot per CPU and a queue timeout of 5 seconds.
+    fn default() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, usize::from);
+        Self::new(cpus, Duration::from_secs(5))
+    }
+}
+
+impl PoolMetrics {
+    /// The average time a finished job has spent running.
+    pub fn average_latency(&self) -> Duration {
+        match self.completed {
+            0 => Duration::ZERO,
+            completed => {
+                let micros = self.total_latency.as_micros() / u128::from(completed);
+                Duration::from_micros(micros as u64)
+            }
+        }
+    }
+}
+
+/// Counts a job as queued or running for as long as it's alive.
+///
+/// This makes sure the job stops being counted if the future waiting for a
+/// free slot is dropped, or if the job panics.
+struct Counted<'a>(&'a AtomicUsize);
+
+impl<'a> Counted<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Adds the duration to a counter of microseconds.
+fn add_micros(counter: &AtomicU64, duration: Duration) {
+    counter.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+}
+
+/// Parses an environment variable, or uses the default if it isn't set.
+fn get_env_or<T: std::str::FromStr>(key: &str, default: T) -> Result<T> {
+    match lerpz_utils::get_env_parse(key) {
+        Err(lerpz_utils::env::Error::NotFound(_)) => Ok(default),
+        res => Ok(res?),
+    }
+}
+
+/// Replaces the pool used for hashing and validating passwords.
+///
+/// Jobs that already started keep using the old pool until they finish.
+pub fn set_hashing_pool(pool: HashingPool) {
+    *POOL.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(pool);
+}
+
+/// Returns the pool used for hashing and validating passwords.
+pub fn hashing_pool() -> Arc<HashingPool> {
+    POOL.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pool_queue_timeout() {
+        let pool = Arc::new(HashingPool::new(1, Duration::from_millis(50)));
+
+        let slow = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                pool.run(
+                    || {
+                        std::thread::sleep(Duration::from_millis(300));
+                        Ok(())
+                    },
+                    Error::FailSpawnBlockForHash,
+                )
+                .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let res = pool.run(|| Ok(()), Error::FailSpawnBlockForHash).await;
+        assert!(matches!(res, Err(Error::QueueTimeout(_))));
+
+        slow.await.unwrap().unwrap();
+        let metrics = pool.metrics();
+        assert_eq!(metrics.completed, 1);
+        assert_eq!(metrics.timed_out, 1);
+        assert_eq!(metrics.queued, 0);
+        assert_eq!(metrics.running, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pool_panicking_job() {
+        let pool = HashingPool::new(1, Duration::from_millis(50));
+
+        let res = pool
+            .run::<(), _>(|| panic!("job failed"), Error::FailSpawnBlockForHash)
+            .await;
+        assert!(matches!(res, Err(Error::FailSpawnBlockForHash)));
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics.running, 0);
+        assert_eq!(metrics.completed, 0);
+        pool.run(|| Ok(()), Error::FailSpawnBlockForHash)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_average_latency() {
+        let metrics = PoolMetrics {
+            queued: 0,
+            running: 0,
+            completed: u64::from(u32::MAX) + 1,
+            timed_out: 0,
+            total_wait: Duration::ZERO,
+            total_latency: Duration::from_micros(u64::from(u32::MAX) + 1) * 3,
+        };
+        assert_eq!(metrics.average_latency(), Duration::from_micros(3));
+    }
+}
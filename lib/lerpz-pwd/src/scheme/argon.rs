@@ -0,0 +1,144 @@
+//! Password scheme using Argon2 with explicit parameters.
+
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{Salt, SaltString},
+};
+use rand::Rng;
+
+use super::{
+    Scheme,
+    error::{Error, Result},
+};
+
+/// A scheme that hashes passwords using Argon2 with the given parameters.
+///
+/// The algorithm, version, parameters and a random salt are all encoded in
+/// the PHC string that gets stored, so validating a hash always uses the
+/// parameters it was made with. Hashes made with other parameters than the
+/// ones of the scheme will be reported by [`Scheme::needs_rehash`].
+///
+/// ### Example
+///
+/// Registering a stronger scheme and using it for new passwords.
+///
+/// ```rust
+/// use lerpz_pwd::argon2::{Algorithm, Params, Version};
+/// use lerpz_pwd::{Argon2Scheme, register_scheme, set_default_scheme};
+///
+/// let params = Params::new(64 * 1024, 3, 1, None).unwrap();
+/// let scheme = Argon2Scheme::new(Algorithm::Argon2id, Version::V0x13, params);
+///
+/// register_scheme("02", scheme).unwrap();
+/// set_default_scheme("02").unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Argon2Scheme {
+    algorithm: Algorithm,
+    version: Version,
+    params: Params,
+}
+
+impl Argon2Scheme {
+    /// Creates a new [`Argon2Scheme`].
+    pub fn new(algorithm: Algorithm, version: Version, params: Params) -> Self {
+        Self {
+            algorithm,
+            version,
+            params,
+        }
+    }
+
+    /// The algorithm variant used for new hashes.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// The Argon2 version used for new hashes.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// The parameters used for new hashes.
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// Returns the [`Argon2`] context for this scheme.
+    fn context(&self) -> Argon2<'static> {
+        Argon2::new(self.algorithm, self.version, self.params.clone())
+    }
+}
+
+impl Default for Argon2Scheme {
+    /// Argon2id version 19 using the default parameters of the [`argon2`]
+    /// crate, which are the OWASP recommended minimums.
+    fn default() -> Self {
+        Self::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+    }
+}
+
+impl Scheme for Argon2Scheme {
+    fn hash(&self, pwd: &str) -> Result<String> {
+        let salt: [u8; Salt::RECOMMENDED_LENGTH] = rand::rng().random();
+        let salt = SaltString::encode_b64(&salt).map_err(Error::PwdHash)?;
+
+        let pwd = self
+            .context()
+            .hash_password(pwd.as_bytes(), &salt)
+            .map_err(Error::PwdHash)?
+            .to_string();
+
+        Ok(pwd)
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str) -> Result<bool> {
+        let pwd_hash_parsed = PasswordHash::new(pwd_hash).map_err(Error::PwdHash)?;
+
+        Ok(self
+            .context()
+            .verify_password(pwd_ref.as_bytes(), &pwd_hash_parsed)
+            .is_ok())
+    }
+
+    fn needs_rehash(&self, pwd_hash: &str) -> bool {
+        let Ok(pwd_hash) = PasswordHash::new(pwd_hash) else {
+            return true;
+        };
+
+        let algorithm = Algorithm::try_from(pwd_hash.algorithm);
+        let version = pwd_hash.version.map(Version::try_from);
+        let params = Params::try_from(&pwd_hash);
+
+        !matches!(algorithm, Ok(algorithm) if algorithm == self.algorithm)
+            || !matches!(version, Some(Ok(version)) if version == self.version)
+            || !matches!(params, Ok(params) if params.m_cost() == self.params.m_cost()
+                && params.t_cost() == self.params.t_cost()
+                && params.p_cost() == self.params.p_cost())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_rehash_on_changed_params() {
+        let weak = Argon2Scheme::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(Params::MIN_M_COST, 1, 1, None).unwrap(),
+        );
+        let strong = Argon2Scheme::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(Params::MIN_M_COST * 2, 2, 1, None).unwrap(),
+        );
+
+        let hash = weak.hash("password").unwrap();
+
+        assert!(!weak.needs_rehash(&hash));
+        assert!(strong.needs_rehash(&hash));
+        assert!(strong.validate(&hash, "password").unwrap());
+    }
+}
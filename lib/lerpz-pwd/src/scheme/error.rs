@@ -11,8 +11,19 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
 pub enum Error {
-	#[error("no scheme named \"{0}\" exist")]
-	SchemeNotFound(String),
-	#[error("error hashing password: {0}")]
-	PwdHash(#[from] Argon2Error),
+    #[error("no scheme named \"{0}\" exist")]
+    SchemeNotFound(String),
+    #[error("a scheme named \"{0}\" already exists")]
+    SchemeAlreadyExists(String),
+    #[error("\"{0}\" is not a valid scheme name")]
+    InvalidSchemeName(String),
+    #[error("error hashing password: {0}")]
+    PwdHash(#[from] Argon2Error),
+    #[error("invalid argon2 parameters: {0}")]
+    Params(argon2::Error),
+    #[error("scheme \"{0}\" can't create new hashes")]
+    HashingNotSupported(String),
+    #[cfg(feature = "legacy")]
+    #[error("error validating bcrypt hash: {0}")]
+    Bcrypt(#[from] bcrypt::BcryptError),
 }
@@ -0,0 +1,145 @@
+//! Verification-only schemes for hashes imported from other systems.
+//!
+//! These schemes can only validate passwords, they refuse to create new
+//! hashes. A hash using one of them always needs to be rehashed, so users are
+//! moved to the default scheme the first time they log in.
+//!
+//! The imported hashes have to be wrapped in the usual envelope:
+//! - `bcrypt`: `#bcrypt#$2b$12$...` (also `$2a$`, `$2x$` and `$2y$`).
+//! - `scrypt`: `#scrypt#$scrypt$ln=15,r=8,p=1$...` in PHC format.
+//! - `pbkdf2`: `#pbkdf2#$pbkdf2-sha256$i=600000,l=32$...` in PHC format.
+
+use argon2::password_hash::{PasswordHash, PasswordVerifier};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+
+use super::{
+    Scheme,
+    error::{Error, Result},
+};
+
+/// Validates bcrypt hashes.
+pub struct BcryptScheme;
+
+impl Scheme for BcryptScheme {
+    fn hash(&self, _pwd: &str) -> Result<String> {
+        Err(Error::HashingNotSupported("bcrypt".into()))
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str) -> Result<bool> {
+        Ok(bcrypt::verify(pwd_ref, pwd_hash)?)
+    }
+
+    fn needs_rehash(&self, _pwd_hash: &str) -> bool {
+        true
+    }
+
+    fn can_hash(&self) -> bool {
+        false
+    }
+}
+
+/// Validates scrypt hashes in PHC format.
+pub struct ScryptScheme;
+
+impl Scheme for ScryptScheme {
+    fn hash(&self, _pwd: &str) -> Result<String> {
+        Err(Error::HashingNotSupported("scrypt".into()))
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str) -> Result<bool> {
+        verify_phc(&Scrypt, pwd_hash, pwd_ref)
+    }
+
+    fn needs_rehash(&self, _pwd_hash: &str) -> bool {
+        true
+    }
+
+    fn can_hash(&self) -> bool {
+        false
+    }
+}
+
+/// Validates PBKDF2 hashes in PHC format.
+///
+/// PBKDF2-SHA256 and PBKDF2-SHA512 are supported.
+pub struct Pbkdf2Scheme;
+
+impl Scheme for Pbkdf2Scheme {
+    fn hash(&self, _pwd: &str) -> Result<String> {
+        Err(Error::HashingNotSupported("pbkdf2".into()))
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str) -> Result<bool> {
+        verify_phc(&Pbkdf2, pwd_hash, pwd_ref)
+    }
+
+    fn needs_rehash(&self, _pwd_hash: &str) -> bool {
+        true
+    }
+
+    fn can_hash(&self) -> bool {
+        false
+    }
+}
+
+/// Validates a password against a PHC string using the given verifier.
+fn verify_phc(verifier: &impl PasswordVerifier, pwd_hash: &str, pwd_ref: &str) -> Result<bool> {
+    let pwd_hash = PasswordHash::new(pwd_hash).map_err(Error::PwdHash)?;
+    Ok(verifier
+        .verify_password(pwd_ref.as_bytes(), &pwd_hash)
+        .is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use argon2::password_hash::{PasswordHasher, SaltString};
+
+    use super::*;
+
+    #[test]
+    fn test_legacy_schemes_validate_only() {
+        let salt = SaltString::encode_b64(b"some_salt").unwrap();
+
+        let bcrypt = bcrypt::hash("password", 4).unwrap();
+        let scrypt = Scrypt
+            .hash_password_customized(
+                b"password",
+                None,
+                None,
+                scrypt::Params::new(4, 8, 1, 32).unwrap(),
+                &salt,
+            )
+            .unwrap()
+            .to_string();
+        let pbkdf2 = Pbkdf2
+            .hash_password_customized(
+                b"password",
+                Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                None,
+                pbkdf2::Params {
+                    rounds: 1000,
+                    output_length: 32,
+                },
+                &salt,
+            )
+            .unwrap()
+            .to_string();
+
+        let cases: [(&dyn Scheme, &str); 3] = [
+            (&BcryptScheme, &bcrypt),
+            (&ScryptScheme, &scrypt),
+            (&Pbkdf2Scheme, &pbkdf2),
+        ];
+
+        for (scheme, hash) in cases {
+            assert!(scheme.validate(hash, "password").unwrap());
+            assert!(!scheme.validate(hash, "drowssap").unwrap());
+            assert!(scheme.needs_rehash(hash));
+            assert!(matches!(
+                scheme.hash("password"),
+                Err(Error::HashingNotSupported(_))
+            ));
+        }
+    }
+}
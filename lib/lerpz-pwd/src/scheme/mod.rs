@@ -1,26 +1,53 @@
 //! Schemas used for password hashing.
 
+/// Password scheme that uses argon2 with explicit parameters.
+pub mod argon;
 /// Hashing and validation schemes errors.
 pub mod error;
+/// Verification-only schemes for bcrypt, scrypt and PBKDF2.
+#[cfg(feature = "legacy")]
+pub mod legacy;
+/// Registry of all schemes that can be used.
+pub mod registry;
 /// Password scheme that uses argon2.
 pub mod scheme_01;
 
-use error::{Error, Result};
-use scheme_01::Scheme01;
+use error::Result;
+
+pub use argon::Argon2Scheme;
+#[cfg(feature = "legacy")]
+pub use legacy::{BcryptScheme, Pbkdf2Scheme, ScryptScheme};
+pub use registry::{
+    SchemeRegistry, default_scheme, get_scheme, register_scheme, set_default_scheme,
+};
 
 /// Implemented by schemes that can hash and validate passwords.
-pub trait Scheme {
+///
+/// Schemes are shared between threads through the [`SchemeRegistry`], which
+/// is why they have to be [`Send`] and [`Sync`].
+pub trait Scheme: Send + Sync {
     /// Hashes a password from some [`PwdParts`](super::parts::PwdParts).
-    fn hash(&self, pwd: &str, salt: &str) -> Result<String>;
-    /// Validate a password hash against a real password and a salt.
-    fn validate(&self, pwd_hash: &str, pwd_ref: &str, pwd_ref_salt: &str) -> Result<bool>;
-}
-
-/// Returns a scheme given a scheme name as a string.
-#[inline]
-pub fn get_scheme(scheme_name: &str) -> Result<impl Scheme> {
-    match scheme_name {
-        "01" => Ok(Scheme01),
-        _ => Err(Error::SchemeNotFound(scheme_name.into())),
+    ///
+    /// The scheme is responsible for generating a salt and storing it as part
+    /// of the returned hash.
+    fn hash(&self, pwd: &str) -> Result<String>;
+    /// Validate a password hash against a real password.
+    ///
+    /// The salt is read from the hash itself.
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str) -> Result<bool>;
+    /// Whether a password hash made by this scheme is outdated.
+    ///
+    /// Schemes can use this to signal that a hash should be replaced, e.g.
+    /// because it was made with weaker parameters than the scheme uses now.
+    /// Defaults to `false`.
+    fn needs_rehash(&self, _pwd_hash: &str) -> bool {
+        false
+    }
+    /// Whether this scheme can create new hashes.
+    ///
+    /// Schemes that return `false` can only validate existing hashes, and
+    /// can't be used as the default scheme. Defaults to `true`.
+    fn can_hash(&self) -> bool {
+        true
     }
 }
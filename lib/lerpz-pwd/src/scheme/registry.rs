@@ -0,0 +1,215 @@
+//! Registry of the schemes available for hashing and validating passwords.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, PoisonError, RwLock},
+};
+
+use regex::Regex;
+
+use super::{
+    Scheme,
+    error::{Error, Result},
+    scheme_01::Scheme01,
+};
+use crate::DEFAULT_SCHEME;
+
+/// A regex that matches valid scheme names.
+///
+/// This has to match the `scheme` group in the regex used by
+/// [`HashParts::from_str`](crate::HashParts), otherwise a hash made with the
+/// scheme could never be parsed again.
+static SCHEME_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\w+$").unwrap());
+
+/// The global registry used by the hashing and validation functions.
+static REGISTRY: LazyLock<RwLock<SchemeRegistry>> =
+    LazyLock::new(|| RwLock::new(SchemeRegistry::default()));
+
+/// A collection of schemes keyed by their name.
+///
+/// The name is what gets written between the `#` characters in front of a
+/// password hash, so a scheme must never be removed or changed while there
+/// are still hashes using it.
+pub struct SchemeRegistry {
+    schemes: HashMap<String, Arc<dyn Scheme>>,
+    default: String,
+}
+
+impl SchemeRegistry {
+    /// Creates a new empty [`SchemeRegistry`].
+    ///
+    /// The registry has no default scheme until one is registered and set
+    /// using [`SchemeRegistry::set_default`].
+    pub fn new() -> Self {
+        Self {
+            schemes: HashMap::new(),
+            default: String::new(),
+        }
+    }
+
+    /// Registers a new scheme under the given name.
+    ///
+    /// Returns an error if the name is not a valid scheme name or if a scheme
+    /// with the same name already exists.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        scheme: impl Scheme + 'static,
+    ) -> Result<()> {
+        let name = name.into();
+
+        if !SCHEME_NAME_REGEX.is_match(&name) {
+            return Err(Error::InvalidSchemeName(name));
+        }
+        if self.schemes.contains_key(&name) {
+            return Err(Error::SchemeAlreadyExists(name));
+        }
+
+        self.schemes.insert(name, Arc::new(scheme));
+        Ok(())
+    }
+
+    /// Sets which scheme is used when hashing new passwords.
+    ///
+    /// Returns an error if no scheme with the given name is registered, or if
+    /// the scheme can't create new hashes.
+    pub fn set_default(&mut self, name: &str) -> Result<()> {
+        if !self.get(name)?.can_hash() {
+            return Err(Error::HashingNotSupported(name.into()));
+        }
+
+        self.default = name.into();
+        Ok(())
+    }
+
+    /// Returns the name of the scheme used when hashing new passwords.
+    pub fn default_scheme(&self) -> &str {
+        &self.default
+    }
+
+    /// Returns the scheme with the given name.
+    pub fn get(&self, name: &str) -> Result<Arc<dyn Scheme>> {
+        self.schemes
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::SchemeNotFound(name.into()))
+    }
+}
+
+impl Default for SchemeRegistry {
+    /// Creates a [`SchemeRegistry`] with all built-in schemes registered.
+    ///
+    /// The default scheme will be [`DEFAULT_SCHEME`]. The verification-only
+    /// `bcrypt`, `scrypt` and `pbkdf2` schemes are registered when the
+    /// `legacy` feature is enabled.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register("01", Scheme01)
+            .expect("built-in scheme names are valid and unique");
+
+        #[cfg(feature = "legacy")]
+        {
+            use super::legacy::{BcryptScheme, Pbkdf2Scheme, ScryptScheme};
+
+            registry
+                .register("bcrypt", BcryptScheme)
+                .and_then(|_| registry.register("scrypt", ScryptScheme))
+                .and_then(|_| registry.register("pbkdf2", Pbkdf2Scheme))
+                .expect("built-in scheme names are valid and unique");
+        }
+
+        registry
+            .set_default(DEFAULT_SCHEME)
+            .expect("default scheme is registered");
+        registry
+    }
+}
+
+/// Returns a scheme from the global registry given its name.
+pub fn get_scheme(scheme_name: &str) -> Result<Arc<dyn Scheme>> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(scheme_name)
+}
+
+/// Registers a scheme in the global registry.
+///
+/// This should be done once when the application starts, before any password
+/// is hashed or validated. See [`SchemeRegistry::register`].
+pub fn register_scheme(name: impl Into<String>, scheme: impl Scheme + 'static) -> Result<()> {
+    REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .register(name, scheme)
+}
+
+/// Sets the scheme used for hashing new passwords in the global registry.
+///
+/// See [`SchemeRegistry::set_default`].
+pub fn set_default_scheme(name: &str) -> Result<()> {
+    REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .set_default(name)
+}
+
+/// Returns the name of the scheme used for hashing new passwords in the
+/// global registry.
+pub fn default_scheme() -> String {
+    REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .default_scheme()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Plain;
+
+    impl Scheme for Plain {
+        fn hash(&self, pwd: &str) -> Result<String> {
+            Ok(format!("salt${pwd}"))
+        }
+
+        fn validate(&self, pwd_hash: &str, pwd_ref: &str) -> Result<bool> {
+            Ok(pwd_hash == format!("salt${pwd_ref}"))
+        }
+    }
+
+    #[test]
+    fn test_register_and_set_default() {
+        let mut registry = SchemeRegistry::default();
+        assert_eq!(registry.default_scheme(), DEFAULT_SCHEME);
+
+        registry.register("plain", Plain).unwrap();
+        registry.set_default("plain").unwrap();
+        assert_eq!(registry.default_scheme(), "plain");
+
+        let scheme = registry.get("plain").unwrap();
+        let hash = scheme.hash("password").unwrap();
+        assert!(scheme.validate(&hash, "password").unwrap());
+    }
+
+    #[test]
+    fn test_register_rejects_invalid_names() {
+        let mut registry = SchemeRegistry::default();
+
+        assert!(matches!(
+            registry.register("01", Plain),
+            Err(Error::SchemeAlreadyExists(_))
+        ));
+        assert!(matches!(
+            registry.register("not#valid", Plain),
+            Err(Error::InvalidSchemeName(_))
+        ));
+        assert!(matches!(
+            registry.set_default("missing"),
+            Err(Error::SchemeNotFound(_))
+        ));
+    }
+}
@@ -1,37 +1,34 @@
-//! Scheme 0 implemented using Argon2.
+//! Scheme 01 implemented using Argon2.
 
 use std::sync::LazyLock;
 
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use argon2::{Algorithm, Params, Version};
 
-use super::{
-    Scheme,
-    error::{Error, Result},
-};
+use super::{Scheme, argon::Argon2Scheme, error::Result};
 
-static ARGON2: LazyLock<Argon2<'static>> = LazyLock::new(|| Argon2::default());
+/// The Argon2 scheme behind [`Scheme01`].
+///
+/// The parameters are spelled out instead of using [`Params::default`], since
+/// they must never change for this scheme. Stronger parameters should be
+/// added as a new scheme instead.
+static ARGON2: LazyLock<Argon2Scheme> = LazyLock::new(|| {
+    let params = Params::new(19 * 1024, 2, 1, None).expect("scheme 01 parameters are valid");
+    Argon2Scheme::new(Algorithm::Argon2id, Version::V0x13, params)
+});
 
+/// Argon2id using 19 MiB of memory, 2 iterations and 1 degree of parallelism.
 pub struct Scheme01;
 
 impl Scheme for Scheme01 {
-    fn hash(&self, pwd: &str, salt: &str) -> Result<String> {
-        let salt = SaltString::encode_b64(salt.as_bytes()).map_err(Error::PwdHash)?;
-
-        let pwd = ARGON2
-            .hash_password(pwd.as_bytes(), &salt)
-            .map_err(Error::PwdHash)?
-            .to_string();
-
-        Ok(pwd)
+    fn hash(&self, pwd: &str) -> Result<String> {
+        ARGON2.hash(pwd)
     }
 
-    fn validate(&self, pwd_hash: &str, pwd_ref: &str, _pwd_ref_salt: &str) -> Result<bool> {
-        let pwd_hash_parsed = PasswordHash::new(pwd_hash).map_err(Error::PwdHash)?;
-
-        let pwd_ref_bytes = pwd_ref.as_bytes();
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str) -> Result<bool> {
+        ARGON2.validate(pwd_hash, pwd_ref)
+    }
 
-        Ok(ARGON2
-            .verify_password(pwd_ref_bytes, &pwd_hash_parsed)
-            .is_ok())
+    fn needs_rehash(&self, pwd_hash: &str) -> bool {
+        ARGON2.needs_rehash(pwd_hash)
     }
 }
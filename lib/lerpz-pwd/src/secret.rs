@@ -0,0 +1,67 @@
+//! A string type for passwords and other secrets.
+
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+/// A string that is wiped from memory when dropped.
+///
+/// Used for plaintext passwords and anything derived from them, so that they
+/// don't linger in freed memory after they have been used. The value is
+/// redacted in [`Debug`], so it can't end up in logs by accident. Use
+/// [`SecretString::expose_secret`] to get the actual value.
+///
+/// ### Example
+///
+/// ```rust
+/// use lerpz_pwd::SecretString;
+///
+/// let pwd = SecretString::from(String::from("Password123!"));
+///
+/// assert_eq!(pwd.expose_secret(), "Password123!");
+/// assert_eq!(format!("{pwd:?}"), "SecretString(\"[REDACTED]\")");
+/// ```
+///
+/// ### Note
+///
+/// Creating a [`SecretString`] from a [`String`] takes over its buffer, while
+/// creating it from a `&str` copies it. In the latter case the original is
+/// not wiped, so prefer moving owned strings into it.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    /// Creates a new [`SecretString`].
+    pub fn new(secret: String) -> Self {
+        Self(Zeroizing::new(secret))
+    }
+
+    /// Returns the secret value.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret.to_string())
+    }
+}
+
+impl From<&SecretString> for SecretString {
+    fn from(secret: &SecretString) -> Self {
+        secret.clone()
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SecretString").field(&"[REDACTED]").finish()
+    }
+}
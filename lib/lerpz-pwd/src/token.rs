@@ -0,0 +1,189 @@
+//! Random tokens for refresh tokens, reset links and API keys.
+//!
+//! A token looks like `lrpz_live_<random><checksum>`:
+//! - The prefix tells what the token is for, and makes it easy for secret
+//!   scanners to find leaked tokens.
+//! - The random part is 32 base62 characters, which is about 190 bits.
+//! - The checksum is the CRC32 of everything before it, encoded as 6 base62
+//!   characters. It lets scanners and the server throw away mistyped or made
+//!   up tokens without a database lookup.
+//!
+//! Tokens are high-entropy, so they don't need a slow hash like passwords do.
+//! Only a keyed hash made by a [`TokenHasher`] should be stored, so that the
+//! database alone isn't enough to use or even verify a token.
+
+use std::{fmt, sync::LazyLock};
+
+use hmac::{Hmac, Mac};
+use rand::{Rng, distr::Alphanumeric};
+use regex::Regex;
+use sha2::Sha256;
+
+use crate::{
+    SecretString,
+    error::{Error, Result},
+};
+
+/// A regex that matches valid token prefixes.
+static TOKEN_PREFIX_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z0-9]+(_[a-z0-9]+)*$").unwrap());
+
+/// The characters used for base62.
+const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Amount of random characters in a token.
+const RANDOM_LEN: usize = 32;
+
+/// Amount of characters in the checksum of a token.
+const CHECKSUM_LEN: usize = 6;
+
+/// Generates a new token with the given prefix, e.g. `lrpz_live`.
+///
+/// The prefix must be lowercase letters and digits, optionally separated by
+/// single underscores.
+///
+/// ### Example
+///
+/// ```rust
+/// let token = lerpz_pwd::generate_token("lrpz_live").unwrap();
+///
+/// assert!(token.expose_secret().starts_with("lrpz_live_"));
+/// assert!(lerpz_pwd::check_token(token.expose_secret()));
+/// ```
+pub fn generate_token(prefix: &str) -> Result<SecretString> {
+    if !TOKEN_PREFIX_REGEX.is_match(prefix) {
+        return Err(Error::InvalidToken(format!(
+            "\"{prefix}\" is not a valid prefix"
+        )));
+    }
+
+    let mut token = String::with_capacity(prefix.len() + 1 + RANDOM_LEN + CHECKSUM_LEN);
+    token.push_str(prefix);
+    token.push('_');
+    token.extend(
+        rand::rng()
+            .sample_iter(Alphanumeric)
+            .take(RANDOM_LEN)
+            .map(char::from),
+    );
+    token.push_str(&checksum(&token));
+
+    Ok(token.into())
+}
+
+/// Whether the token is well formed and its checksum is correct.
+///
+/// This doesn't say anything about whether the token was ever issued, only
+/// that it wasn't mistyped or made up without care.
+pub fn check_token(token: &str) -> bool {
+    let Some((body, sum)) = token.split_at_checked(token.len().saturating_sub(CHECKSUM_LEN)) else {
+        return false;
+    };
+    let Some((prefix, random)) = body.rsplit_once('_') else {
+        return false;
+    };
+
+    TOKEN_PREFIX_REGEX.is_match(prefix)
+        && random.len() == RANDOM_LEN
+        && random.bytes().all(|b| b.is_ascii_alphanumeric())
+        && checksum(body) == sum
+}
+
+/// Returns the prefix of the token, e.g. `lrpz_live`.
+///
+/// Returns [`None`] if the token isn't valid according to [`check_token`].
+pub fn token_prefix(token: &str) -> Option<&str> {
+    if !check_token(token) {
+        return None;
+    }
+    token.rsplit_once('_').map(|(prefix, _)| prefix)
+}
+
+/// Makes the keyed hashes of tokens that get stored.
+///
+/// The hash is HMAC-SHA256 keyed with a server-side secret and encoded as
+/// hex, so it can be used to look up tokens in the database.
+#[derive(Clone)]
+pub struct TokenHasher {
+    key: Vec<u8>,
+}
+
+impl TokenHasher {
+    /// Creates a new [`TokenHasher`] with the given key.
+    ///
+    /// Returns an error if the key is shorter than 32 bytes.
+    pub fn new(key: impl Into<Vec<u8>>) -> Result<Self> {
+        let key = key.into();
+        if key.len() < 32 {
+            return Err(Error::InvalidToken(
+                "hashing key must be at least 32 bytes".into(),
+            ));
+        }
+        Ok(Self { key })
+    }
+
+    /// Loads the key from the `TOKEN_HASH_KEY` environment variable, which
+    /// must be hex encoded.
+    pub fn from_env() -> Result<Self> {
+        let key = hex::decode(lerpz_utils::get_env("TOKEN_HASH_KEY")?)
+            .map_err(|_| Error::InvalidToken("hashing key is not hex encoded".into()))?;
+        Self::new(key)
+    }
+
+    /// Returns the hash of the token that should be stored.
+    pub fn hash(&self, token: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(token.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+impl fmt::Debug for TokenHasher {
+    /// Doesn't show the key, so that it doesn't end up in logs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenHasher").finish_non_exhaustive()
+    }
+}
+
+/// Returns the CRC32 of the value encoded as fixed length base62.
+fn checksum(value: &str) -> String {
+    let mut crc = crc32fast::hash(value.as_bytes());
+    let mut out = [b'0'; CHECKSUM_LEN];
+    for slot in out.iter_mut().rev() {
+        *slot = BASE62[(crc % 62) as usize];
+        crc /= 62;
+    }
+    String::from_utf8(out.to_vec()).expect("base62 is valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_checksum() {
+        let token = generate_token("lrpz_live").unwrap();
+        let token = token.expose_secret();
+
+        assert_eq!(token.len(), "lrpz_live_".len() + RANDOM_LEN + CHECKSUM_LEN);
+        assert!(check_token(token));
+        assert_eq!(token_prefix(token), Some("lrpz_live"));
+
+        let mut typo = token.to_string().into_bytes();
+        typo[12] = if typo[12] == b'a' { b'b' } else { b'a' };
+        assert!(!check_token(std::str::from_utf8(&typo).unwrap()));
+        assert!(!check_token("lrpz_live_"));
+        assert!(generate_token("Not Valid").is_err());
+    }
+
+    #[test]
+    fn test_token_hasher() {
+        let hasher = TokenHasher::new([7u8; 32]).unwrap();
+        let other = TokenHasher::new([8u8; 32]).unwrap();
+
+        assert_eq!(hasher.hash("token"), hasher.hash("token"));
+        assert_ne!(hasher.hash("token"), other.hash("token"));
+        assert!(TokenHasher::new([7u8; 16]).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Picking Argon2 parameters that fit the machine the code is running on.
+
+use std::time::{Duration, Instant};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+
+use crate::{
+    Argon2Scheme,
+    error::{Error, Result},
+    scheme::error::Error as SchemeError,
+};
+
+/// Finds the Argon2 memory cost that makes hashing take a target duration.
+///
+/// The number of iterations and the degree of parallelism are fixed, and only
+/// the memory cost is tuned, as recommended by RFC 9106. The time it takes to
+/// hash a password grows roughly linearly with the memory cost, which is what
+/// the tuner uses to find a fitting memory cost in a few measurements.
+///
+/// ### Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use lerpz_pwd::Tuner;
+///
+/// let params = Tuner::new(Duration::from_millis(50))
+///     .with_memory_range(8 * 1024, 64 * 1024)
+///     .tune()
+///     .unwrap();
+///
+/// assert!(params.m_cost() >= 8 * 1024);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    target: Duration,
+    algorithm: Algorithm,
+    version: Version,
+    t_cost: u32,
+    p_cost: u32,
+    min_m_cost: u32,
+    max_m_cost: u32,
+    samples: u32,
+}
+
+impl Tuner {
+    /// The most times the memory cost is adjusted before giving up.
+    const MAX_ROUNDS: u32 = 4;
+
+    /// Creates a new [`Tuner`] aiming for the given hashing duration.
+    ///
+    /// Defaults to Argon2id with 2 iterations, 1 degree of parallelism and a
+    /// memory cost between 19 MiB and 1 GiB.
+    pub fn new(target: Duration) -> Self {
+        Self {
+            target,
+            algorithm: Algorithm::Argon2id,
+            version: Version::V0x13,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+            min_m_cost: Params::DEFAULT_M_COST,
+            max_m_cost: 1024 * 1024,
+            samples: 3,
+        }
+    }
+
+    /// Sets the algorithm variant to tune for.
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the number of iterations.
+    pub fn with_iterations(mut self, t_cost: u32) -> Self {
+        self.t_cost = t_cost;
+        self
+    }
+
+    /// Sets the degree of parallelism.
+    pub fn with_parallelism(mut self, p_cost: u32) -> Self {
+        self.p_cost = p_cost;
+        self
+    }
+
+    /// Sets the lowest and highest memory cost in KiB the tuner may pick.
+    pub fn with_memory_range(mut self, min_m_cost: u32, max_m_cost: u32) -> Self {
+        self.min_m_cost = min_m_cost;
+        self.max_m_cost = max_m_cost.max(min_m_cost);
+        self
+    }
+
+    /// Sets how many times each set of parameters is measured.
+    ///
+    /// The median of the measurements is used.
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Finds the parameters that are closest to the target without exceeding
+    /// it by more than 10%.
+    ///
+    /// If even the lowest memory cost is slower than the target, the lowest
+    /// memory cost is returned.
+    pub fn tune(&self) -> Result<Params> {
+        let limit = self.target.mul_f64(1.1);
+        let mut best = self.min_m_cost;
+        let mut m_cost = self.min_m_cost;
+
+        for _ in 0..Self::MAX_ROUNDS {
+            let elapsed = self.measure(&self.params(m_cost)?)?;
+            if elapsed <= limit {
+                best = best.max(m_cost);
+            }
+
+            let ratio = self.target.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON);
+            let next = ((m_cost as f64 * ratio) as u32).clamp(self.min_m_cost, self.max_m_cost);
+            if next.abs_diff(m_cost) <= m_cost / 20 {
+                break;
+            }
+
+            m_cost = next;
+        }
+
+        self.params(best)
+    }
+
+    /// Same as [`Tuner::tune`], but returns a ready to use scheme.
+    pub fn tune_scheme(&self) -> Result<Argon2Scheme> {
+        Ok(Argon2Scheme::new(
+            self.algorithm,
+            self.version,
+            self.tune()?,
+        ))
+    }
+
+    /// Measures how long it takes to hash a password with the parameters.
+    ///
+    /// Returns the median of the configured amount of samples.
+    pub fn measure(&self, params: &Params) -> Result<Duration> {
+        let argon2 = Argon2::new(self.algorithm, self.version, params.clone());
+        let mut out = [0u8; Params::DEFAULT_OUTPUT_LEN];
+
+        let mut samples = (0..self.samples)
+            .map(|_| {
+                let start = Instant::now();
+                argon2
+                    .hash_password_into(b"#Password123!", b"some_salt", &mut out)
+                    .map_err(|err| Error::SchemeError(SchemeError::Params(err)))?;
+                Ok(start.elapsed())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        samples.sort();
+        Ok(samples[samples.len() / 2])
+    }
+
+    /// Creates [`Params`] with the given memory cost.
+    fn params(&self, m_cost: u32) -> Result<Params> {
+        Params::new(m_cost, self.t_cost, self.p_cost, None)
+            .map_err(|err| Error::SchemeError(SchemeError::Params(err)))
+    }
+}
@@ -0,0 +1,35 @@
+/// The outcome of verifying a password against a stored hash.
+///
+/// Unlike the plain [`bool`] returned by [`validate_pwd`](crate::validate_pwd)
+/// this also tells the caller if the stored hash should be replaced, because
+/// it was made with an outdated scheme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The password did not match the hash.
+    Invalid,
+    /// The password matched the hash.
+    Valid {
+        /// Whether the stored hash was made with an outdated scheme.
+        needs_rehash: bool,
+        /// A new hash of the password using the default scheme.
+        ///
+        /// This is only [`Some`] when `needs_rehash` is `true`, and should be
+        /// stored in place of the old hash.
+        new_hash: Option<String>,
+    },
+}
+
+impl Verdict {
+    /// Returns `true` if the password matched the hash.
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Verdict::Valid { .. })
+    }
+
+    /// Returns the new hash if the stored hash needs to be replaced.
+    pub fn new_hash(&self) -> Option<&str> {
+        match self {
+            Verdict::Valid { new_hash, .. } => new_hash.as_deref(),
+            Verdict::Invalid => None,
+        }
+    }
+}
@@ -0,0 +1,4 @@
+-- Track users whose password was found in a breach when they logged in
+
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS password_breached_at TIMESTAMPTZ DEFAULT NULL;
@@ -0,0 +1,6 @@
+-- Password hashes store their own salt, so the separate salt is no longer
+-- needed. The column is kept for now, but will be dropped in a later release.
+
+ALTER TABLE users
+    ALTER COLUMN password_salt DROP NOT NULL,
+    ALTER COLUMN password_salt SET DEFAULT NULL;
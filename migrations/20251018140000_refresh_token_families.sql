@@ -0,0 +1,13 @@
+-- Refresh tokens are rotated on every use. All tokens rotated from the same
+-- login share a family, so that the whole family can be revoked when a token
+-- that was already used shows up again.
+--
+-- The `token` column now holds a keyed hash of the token, never the token.
+
+ALTER TABLE refresh_tokens
+    ADD COLUMN IF NOT EXISTS family_id UUID NOT NULL DEFAULT uuid_generate_v4(),
+    ADD COLUMN IF NOT EXISTS parent_id UUID DEFAULT NULL REFERENCES refresh_tokens(id),
+    ADD COLUMN IF NOT EXISTS scope TEXT DEFAULT NULL,
+    ADD COLUMN IF NOT EXISTS used_at TIMESTAMPTZ DEFAULT NULL;
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id_idx ON refresh_tokens(family_id);
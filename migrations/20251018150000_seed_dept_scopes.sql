@@ -0,0 +1,20 @@
+-- Scopes protecting the department endpoints. Being granted `dept` also
+-- grants `dept.read` and `dept.write`.
+
+INSERT INTO scopes(name, description) VALUES
+    ('dept', 'Full access to departments.')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO scopes(name, description, parent_scope_id)
+SELECT child.name, child.description, parent.id
+FROM (VALUES
+    ('dept.read', 'Read departments.'),
+    ('dept.write', 'Create, update and delete departments.')
+) AS child(name, description)
+CROSS JOIN scopes parent
+WHERE parent.name = 'dept'
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO client_scopes(client_id, scope_id)
+SELECT 'cdd37e5a-a554-4535-bff2-45ba130b05b4', id FROM scopes WHERE name = 'dept'
+ON CONFLICT DO NOTHING;
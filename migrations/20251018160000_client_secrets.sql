@@ -0,0 +1,39 @@
+-- Secrets of confidential OAuth clients, hashed like passwords.
+--
+-- A client can have two valid secrets at once, so that a secret can be
+-- rotated without downtime. When a secret is rotated, the previous one gets
+-- an `expires_at` and any older one is revoked.
+
+CREATE TABLE IF NOT EXISTS client_secrets(
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    client_id UUID NOT NULL REFERENCES oauth_clients(id),
+    secret_hash VARCHAR(512) NOT NULL,
+    expires_at TIMESTAMPTZ DEFAULT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMPTZ DEFAULT NULL
+);
+
+CREATE INDEX IF NOT EXISTS client_secrets_client_id_idx ON client_secrets(client_id);
+
+CREATE TRIGGER update_timestamp
+    BEFORE UPDATE ON client_secrets
+    FOR EACH ROW
+    EXECUTE FUNCTION update_timestamp();
+
+-- Scopes protecting the client management endpoints. They aren't granted to
+-- the portal, only to the clients used to manage other clients.
+
+INSERT INTO scopes(name, description) VALUES
+    ('clients', 'Full access to OAuth clients.')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO scopes(name, description, parent_scope_id)
+SELECT child.name, child.description, parent.id
+FROM (VALUES
+    ('clients.read', 'Read OAuth clients.'),
+    ('clients.write', 'Create, update and delete OAuth clients and their secrets.')
+) AS child(name, description)
+CROSS JOIN scopes parent
+WHERE parent.name = 'clients'
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,8 @@
+-- Access tokens issued together with a refresh token share its family, so
+-- that revoking the refresh token also revokes the access tokens issued from
+-- the same grant. Tokens from the `client_credentials` grant have no family.
+
+ALTER TABLE access_tokens
+    ADD COLUMN IF NOT EXISTS family_id UUID DEFAULT NULL;
+
+CREATE INDEX IF NOT EXISTS access_tokens_family_id_idx ON access_tokens(family_id);
@@ -0,0 +1,9 @@
+-- Scopes defined by OpenID Connect. Clients must be granted `openid` to get
+-- an ID token, and `profile` or `email` to read those claims from
+-- `/oauth/userinfo`.
+
+INSERT INTO scopes(name, description) VALUES
+    ('openid', 'Sign in with Lerpz.'),
+    ('profile', 'Read your username and avatar.'),
+    ('email', 'Read your email address.')
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,16 @@
+-- The scopes a user has consented to give each client. A user is only asked
+-- again when a client requests scopes that aren't covered yet.
+
+CREATE TABLE IF NOT EXISTS user_consents(
+    user_id UUID NOT NULL REFERENCES users(id),
+    client_id UUID NOT NULL REFERENCES oauth_clients(id),
+    scope TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, client_id)
+);
+
+CREATE TRIGGER update_timestamp
+    BEFORE UPDATE ON user_consents
+    FOR EACH ROW
+    EXECUTE FUNCTION update_timestamp();
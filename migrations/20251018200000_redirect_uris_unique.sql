@@ -0,0 +1,11 @@
+-- A redirect URI can only be registered once per client, so that concurrent
+-- registrations of the same URI can't both succeed.
+
+DELETE FROM redirect_uris a
+USING redirect_uris b
+WHERE a.client_id = b.client_id
+  AND a.uri = b.uri
+  AND a.id > b.id;
+
+ALTER TABLE redirect_uris
+    ADD CONSTRAINT redirect_uris_client_id_uri_key UNIQUE (client_id, uri);
@@ -0,0 +1,16 @@
+-- Client used to manage the other clients through the client endpoints.
+--
+-- It is granted the `clients` scope, but has no secret yet. The first secret
+-- is issued by portal-api on startup when `ADMIN_SECRET_FILE` is set, and
+-- written to that file. After that it can be rotated through the API.
+
+INSERT INTO oauth_clients(id, name, description) VALUES (
+    '5f0b8c1e-7d2a-4c61-9e3b-2a8d4f6c1b70',
+    'Lerpz Admin',
+    'Manages the other OAuth clients.'
+)
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO client_scopes(client_id, scope_id)
+SELECT '5f0b8c1e-7d2a-4c61-9e3b-2a8d4f6c1b70', id FROM scopes WHERE name = 'clients'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,170 @@
+use axum::{Json, extract::State, http::StatusCode};
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::validate::Validated,
+};
+use lerpz_jwt::KeyRing;
+use lerpz_model::User;
+use lerpz_pwd::{BreachChecker, SecretString, TokenHasher, Verdict, hash_pwd, verify_pwd};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::sync::OnceCell;
+use validator::Validate;
+
+use crate::tokens::{PORTAL_CLIENT_ID, TokenResponse, client_scopes, issue_pair};
+
+#[derive(Deserialize, Validate)]
+pub struct LoginRequest {
+    #[validate(length(min = 1, max = 32, message = "Username must be 1 to 32 characters."))]
+    pub username: String,
+    #[validate(length(min = 1, message = "Password is required."))]
+    pub password: String,
+}
+
+/// Hash that passwords are checked against when the username doesn't exist.
+///
+/// Verifying against it takes as long as verifying a real password, so the
+/// response time doesn't reveal which usernames exist.
+static DUMMY_HASH: OnceCell<String> = OnceCell::const_new();
+
+/// Validates the credentials of a user, and issues tokens for the portal.
+///
+/// If the stored password hash was made with an outdated scheme, it is
+/// replaced with a new hash in the same transaction that issues the tokens,
+/// unless the password was changed in the meantime.
+///
+/// Since this is the only time the plaintext password is known, it is also
+/// checked against the breached passwords, and the outcome is stored in
+/// `users.password_breached_at`.
+///
+/// The password is verified outside of any transaction, so that slow hashing
+/// doesn't hold a database connection or lock the user.
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(breaches): State<Option<BreachChecker>>,
+    State(keys): State<KeyRing>,
+    State(hasher): State<TokenHasher>,
+    Validated(Json(body)): Validated<Json<LoginRequest>>,
+) -> HandlerResult<Json<TokenResponse>> {
+    let password = SecretString::from(body.password);
+
+    let user: Option<User> = sqlx::query_as("SELECT * FROM users WHERE username = $1")
+        .bind(&body.username)
+        .fetch_optional(&database)
+        .await?;
+    let Some(user) = user else {
+        let dummy_hash = DUMMY_HASH
+            .get_or_try_init(|| hash_pwd("dummy password"))
+            .await
+            .map_err(pwd_error)?;
+        verify_pwd(dummy_hash, password).await.map_err(pwd_error)?;
+        return Err(invalid_credentials());
+    };
+
+    let verdict = verify_pwd(&user.password_hash, &password)
+        .await
+        .map_err(pwd_error)?;
+    let new_hash = match verdict {
+        Verdict::Invalid => return Err(invalid_credentials()),
+        Verdict::Valid { new_hash, .. } => new_hash,
+    };
+
+    if let Some(breaches) = breaches {
+        audit_breached(&database, &breaches, &user, &password).await?;
+    }
+
+    let mut tx = database.begin().await?;
+    if let Some(new_hash) = new_hash {
+        let upgraded =
+            sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3")
+                .bind(new_hash)
+                .bind(user.id)
+                .bind(&user.password_hash)
+                .execute(&mut *tx)
+                .await?;
+        if upgraded.rows_affected() > 0 {
+            tracing::info!(user_id = %user.id, "upgraded outdated password hash");
+        }
+    }
+
+    let scopes = client_scopes(&mut *tx, PORTAL_CLIENT_ID).await?;
+    let response = issue_pair(
+        &mut tx,
+        &keys,
+        &hasher,
+        &user,
+        PORTAL_CLIENT_ID,
+        &scopes,
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(response))
+}
+
+/// Records whether the password of the user has appeared in breaches.
+///
+/// Only writes to the database if the outcome changed since the last login.
+/// Failing to read the breached passwords is logged, but doesn't stop the
+/// user from logging in.
+async fn audit_breached(
+    database: &PgPool,
+    breaches: &BreachChecker,
+    user: &User,
+    password: &SecretString,
+) -> HandlerResult<()> {
+    let count = match breaches.count(password).await {
+        Ok(count) => count,
+        Err(err) => {
+            tracing::error!(user_id = %user.id, error = %err, "failed checking password for breaches");
+            return Ok(());
+        }
+    };
+    if (count > 0) == user.password_breached_at.is_some() {
+        return Ok(());
+    }
+
+    if count > 0 {
+        tracing::warn!(user_id = %user.id, count, "user is using a breached password");
+    }
+
+    sqlx::query(
+        "UPDATE users SET password_breached_at = CASE WHEN $1 THEN CURRENT_TIMESTAMP END WHERE id = $2",
+    )
+    .bind(count > 0)
+    .bind(user.id)
+    .execute(database)
+    .await?;
+
+    Ok(())
+}
+
+/// Turns errors from verifying passwords into a [`HandlerError`].
+///
+/// Running out of hashing slots means the server is overloaded, so the client
+/// is told to try again later instead of getting an internal server error.
+fn pwd_error(err: lerpz_pwd::Error) -> HandlerError {
+    match err {
+        lerpz_pwd::Error::QueueTimeout(_) => HandlerError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Service unavailable",
+            "Too many logins are being processed, try again later.",
+        )
+        .with_error(err),
+        err => err.into(),
+    }
+}
+
+/// Error returned when the username or password is wrong.
+///
+/// The same error is used for both, so that it can't be used to find out
+/// which usernames exist.
+fn invalid_credentials() -> HandlerError {
+    HandlerError::new(
+        StatusCode::UNAUTHORIZED,
+        "Invalid credentials",
+        "The username or password is incorrect.",
+    )
+}
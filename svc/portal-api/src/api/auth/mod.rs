@@ -0,0 +1,13 @@
+use crate::state::AppState;
+
+use axum::{Router, routing::post};
+
+mod login;
+mod refresh;
+
+pub fn router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/login", post(login::handler))
+        .route("/refresh", post(refresh::handler))
+        .with_state(state)
+}
@@ -0,0 +1,79 @@
+use axum::{Json, extract::State, http::StatusCode};
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::{jwt::RevocationList, validate::Validated},
+};
+use lerpz_jwt::KeyRing;
+use lerpz_model::User;
+use lerpz_pwd::TokenHasher;
+use serde::Deserialize;
+use sqlx::PgPool;
+use validator::Validate;
+
+use crate::tokens::{
+    PORTAL_CLIENT_ID, TokenResponse, issue_pair,
+    refresh::{self, RefreshError},
+    split_scopes,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct RefreshRequest {
+    #[validate(length(min = 1, max = 512, message = "Refresh token is required."))]
+    pub refresh_token: String,
+}
+
+/// Exchanges a refresh token for a new access and refresh token.
+///
+/// The refresh token can only be used once. Using it again revokes every
+/// refresh token rotated from the same login, and the access tokens issued
+/// with them.
+///
+/// Only refresh tokens issued to the portal can be used here. Other clients
+/// must refresh through `/oauth/token`, which authenticates them.
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(revocations): State<RevocationList>,
+    State(keys): State<KeyRing>,
+    State(hasher): State<TokenHasher>,
+    Validated(Json(body)): Validated<Json<RefreshRequest>>,
+) -> HandlerResult<Json<TokenResponse>> {
+    let (mut tx, parent) = refresh::redeem(&database, &revocations, &hasher, &body.refresh_token)
+        .await
+        .map_err(refresh_error)?;
+    if parent.client_id != PORTAL_CLIENT_ID {
+        return Err(refresh_error(RefreshError::Invalid));
+    }
+
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = $1")
+        .bind(parent.user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let scopes = split_scopes(parent.scope.as_deref());
+    let response = issue_pair(
+        &mut tx,
+        &keys,
+        &hasher,
+        &user,
+        parent.client_id,
+        &scopes,
+        Some(&parent),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(response))
+}
+
+/// Turns errors from redeeming a refresh token into a [`HandlerError`].
+fn refresh_error(err: RefreshError) -> HandlerError {
+    match err {
+        RefreshError::Invalid | RefreshError::Reused(_) => HandlerError::new(
+            StatusCode::UNAUTHORIZED,
+            "Invalid refresh token",
+            "The refresh token is invalid, expired or was already used.",
+        ),
+        err => err.into(),
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeSet;
+
+use axum::{Json, extract::State, http::StatusCode};
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::validate::Validated,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{ClientDetails, redirect_uris, scopes};
+use crate::oauth::secret;
+
+#[derive(Deserialize, Validate)]
+pub struct CreateClient {
+    #[validate(length(min = 1, max = 64, message = "Name must be 1 to 64 characters."))]
+    pub name: String,
+    #[validate(length(max = 1000, message = "Description must be at most 1000 characters."))]
+    pub description: Option<String>,
+    pub organization_id: Option<Uuid>,
+    #[serde(default)]
+    #[validate(custom(function = "redirect_uris::validate_redirect_uris"))]
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub scopes: BTreeSet<String>,
+    /// Whether to issue a secret, which makes it a confidential client.
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreatedClient {
+    #[serde(flatten)]
+    pub client: ClientDetails,
+    /// The secret of a confidential client. It can't be read again later.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+/// Registers a new client.
+pub async fn handler(
+    State(database): State<PgPool>,
+    Validated(Json(body)): Validated<Json<CreateClient>>,
+) -> HandlerResult<(StatusCode, Json<CreatedClient>)> {
+    let mut tx = database.begin().await?;
+
+    let client_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO oauth_clients(name, description, organization_id) VALUES ($1, $2, $3) \
+         ON CONFLICT (name) DO NOTHING RETURNING id",
+    )
+    .bind(&body.name)
+    .bind(&body.description)
+    .bind(body.organization_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        HandlerError::new(
+            StatusCode::CONFLICT,
+            "Client already exists",
+            "A client with the given name already exists.",
+        )
+    })?;
+
+    for uri in &body.redirect_uris {
+        redirect_uris::insert(&mut *tx, client_id, uri).await?;
+    }
+    scopes::assign(&mut tx, client_id, &body.scopes).await?;
+
+    let client_secret = match body.confidential {
+        true => Some(secret::rotate(&mut tx, client_id).await?.0),
+        false => None,
+    };
+    tx.commit().await?;
+    tracing::info!(%client_id, name = %body.name, "registered client");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedClient {
+            client: super::client_details(&database, client_id).await?,
+            client_secret: client_secret.map(|secret| secret.expose_secret().to_string()),
+        }),
+    ))
+}
@@ -0,0 +1,40 @@
+use axum::{Json, extract::State};
+use chrono::{DateTime, Utc};
+use lerpz_axum::error::HandlerResult;
+use lerpz_model::OAuthClient;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A client as shown in a list.
+#[derive(Serialize, Debug)]
+pub struct ClientSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub organization_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<OAuthClient> for ClientSummary {
+    fn from(client: OAuthClient) -> Self {
+        Self {
+            id: client.id,
+            name: client.name,
+            description: client.description,
+            organization_id: client.organization_id,
+            created_at: client.created_at,
+            updated_at: client.updated_at,
+        }
+    }
+}
+
+/// Lists every client.
+pub async fn handler(State(database): State<PgPool>) -> HandlerResult<Json<Vec<ClientSummary>>> {
+    let clients: Vec<OAuthClient> = sqlx::query_as("SELECT * FROM oauth_clients ORDER BY name")
+        .fetch_all(&database)
+        .await?;
+
+    Ok(Json(clients.into_iter().map(Into::into).collect()))
+}
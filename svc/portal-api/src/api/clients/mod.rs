@@ -0,0 +1,180 @@
+use crate::state::AppState;
+
+use axum::{
+    Router,
+    extract::FromRequestParts,
+    handler::Handler,
+    http::{StatusCode, request::Parts},
+    routing::{delete, get, post, put},
+};
+use chrono::{DateTime, Utc};
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::{
+        guard::{Permissions, RequireScope},
+        jwt::{AccessToken, Unrevoked},
+    },
+};
+use lerpz_jwt::{Claims, HasClaims};
+use lerpz_model::{OAuthClient, RedirectUri};
+use serde::Serialize;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::tokens::client_scopes;
+
+mod create;
+mod list;
+mod read;
+mod redirect_uris;
+mod scopes;
+mod secret;
+
+/// The token required by the client endpoints.
+///
+/// These are meant to be called by other clients using the
+/// `client_credentials` grant, so only tokens issued to a client itself are
+/// accepted. Tokens issued to a user are rejected, even if they have the scope.
+struct Token(Unrevoked<AccessToken<Claims>>);
+
+impl FromRequestParts<AppState> for Token {
+    type Rejection = HandlerError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = Unrevoked::<AccessToken<Claims>>::from_request_parts(parts, state).await?;
+        let claims = token.0.claims();
+
+        let issued_to_client: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM access_tokens \
+             WHERE jti = $1 AND user_id IS NULL AND client_id::text = $2)",
+        )
+        .bind(&claims.jti)
+        .bind(&claims.sub)
+        .fetch_one(&state.database)
+        .await?;
+        if !issued_to_client {
+            return Err(HandlerError::forbidden());
+        }
+
+        Ok(Self(token))
+    }
+}
+
+impl Permissions for Token {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.0.has_scope(scope)
+    }
+
+    fn has_role(&self, role: &str) -> bool {
+        self.0.has_role(role)
+    }
+}
+
+pub fn router(state: AppState) -> Router<AppState> {
+    let read = RequireScope::<Token, _>::new(state.clone(), "clients.read")
+        .with_hierarchy(state.scopes.clone());
+    let write = RequireScope::<Token, _>::new(state.clone(), "clients.write")
+        .with_hierarchy(state.scopes.clone());
+
+    Router::new()
+        .route(
+            "/",
+            post(create::handler.layer(write.clone())).get(list::handler.layer(read.clone())),
+        )
+        .route("/{id}", get(read::handler.layer(read)))
+        .route(
+            "/{id}/redirect_uris",
+            post(redirect_uris::add.layer(write.clone())),
+        )
+        .route(
+            "/{id}/redirect_uris/{uri_id}",
+            delete(redirect_uris::remove.layer(write.clone())),
+        )
+        .route("/{id}/scopes", put(scopes::handler.layer(write.clone())))
+        .route("/{id}/secret", post(secret::handler.layer(write)))
+        .with_state(state)
+}
+
+/// A client along with its redirect URIs and scopes.
+#[derive(Serialize, Debug)]
+pub struct ClientDetails {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub organization_id: Option<Uuid>,
+    /// Whether the client has ever been issued a secret.
+    pub confidential: bool,
+    pub redirect_uris: Vec<RedirectUriDetails>,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RedirectUriDetails {
+    pub id: Uuid,
+    pub uri: String,
+}
+
+impl From<RedirectUri> for RedirectUriDetails {
+    fn from(uri: RedirectUri) -> Self {
+        Self {
+            id: uri.id,
+            uri: uri.uri,
+        }
+    }
+}
+
+/// Returns the client with its redirect URIs and scopes.
+///
+/// Returns a `404 Not Found` error if the client doesn't exist.
+async fn client_details<'c, E>(executor: E, id: Uuid) -> HandlerResult<ClientDetails>
+where
+    E: PgExecutor<'c> + Copy,
+{
+    let client: OAuthClient = sqlx::query_as("SELECT * FROM oauth_clients WHERE id = $1")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(client_not_found)?;
+
+    let redirect_uris: Vec<RedirectUri> =
+        sqlx::query_as("SELECT * FROM redirect_uris WHERE client_id = $1 ORDER BY created_at")
+            .bind(id)
+            .fetch_all(executor)
+            .await?;
+
+    Ok(ClientDetails {
+        confidential: crate::oauth::secret::is_confidential(executor, id).await?,
+        redirect_uris: redirect_uris.into_iter().map(Into::into).collect(),
+        scopes: client_scopes(executor, id).await?.into_iter().collect(),
+        id: client.id,
+        name: client.name,
+        description: client.description,
+        organization_id: client.organization_id,
+        created_at: client.created_at,
+        updated_at: client.updated_at,
+    })
+}
+
+/// Returns a `404 Not Found` error if the client doesn't exist.
+async fn ensure_exists(executor: impl PgExecutor<'_>, id: Uuid) -> HandlerResult<()> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM oauth_clients WHERE id = $1)")
+            .bind(id)
+            .fetch_one(executor)
+            .await?;
+    exists.then_some(()).ok_or_else(client_not_found)
+}
+
+/// Error returned when a client doesn't exist.
+fn client_not_found() -> HandlerError {
+    HandlerError::new(
+        StatusCode::NOT_FOUND,
+        "Client not found",
+        "No client with the given id exists.",
+    )
+}
@@ -0,0 +1,17 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use lerpz_axum::error::HandlerResult;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::ClientDetails;
+
+/// Returns a client along with its redirect URIs and scopes.
+pub async fn handler(
+    State(database): State<PgPool>,
+    Path(client_id): Path<Uuid>,
+) -> HandlerResult<Json<ClientDetails>> {
+    Ok(Json(super::client_details(&database, client_id).await?))
+}
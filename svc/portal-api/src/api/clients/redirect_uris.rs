@@ -0,0 +1,165 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use fluent_uri::Uri;
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::validate::Validated,
+};
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::RedirectUriDetails;
+
+/// The longest redirect URI that can be stored.
+const MAX_LEN: usize = 2000;
+
+/// Hosts that may use plain `http`, since they never leave the machine.
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+#[derive(Deserialize, Validate)]
+pub struct AddRedirectUri {
+    #[validate(custom(function = "validate_redirect_uri"))]
+    pub uri: String,
+}
+
+/// Registers a redirect URI for a client.
+pub async fn add(
+    State(database): State<PgPool>,
+    Path(client_id): Path<Uuid>,
+    Validated(Json(body)): Validated<Json<AddRedirectUri>>,
+) -> HandlerResult<(StatusCode, Json<RedirectUriDetails>)> {
+    let mut tx = database.begin().await?;
+    super::ensure_exists(&mut *tx, client_id).await?;
+
+    let uri = insert(&mut *tx, client_id, &body.uri)
+        .await?
+        .ok_or_else(|| {
+            HandlerError::new(
+                StatusCode::CONFLICT,
+                "Redirect URI already registered",
+                "The redirect URI is already registered for the client.",
+            )
+        })?;
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(uri)))
+}
+
+/// Removes a redirect URI from a client.
+pub async fn remove(
+    State(database): State<PgPool>,
+    Path((client_id, uri_id)): Path<(Uuid, Uuid)>,
+) -> HandlerResult<StatusCode> {
+    let result = sqlx::query("DELETE FROM redirect_uris WHERE id = $1 AND client_id = $2")
+        .bind(uri_id)
+        .bind(client_id)
+        .execute(&database)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(HandlerError::new(
+            StatusCode::NOT_FOUND,
+            "Redirect URI not found",
+            "The client has no redirect URI with the given id.",
+        ));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Registers a redirect URI for a client.
+///
+/// Returns [`None`] if the URI is already registered for the client.
+pub async fn insert(
+    executor: impl PgExecutor<'_>,
+    client_id: Uuid,
+    uri: &str,
+) -> sqlx::Result<Option<RedirectUriDetails>> {
+    let inserted: Option<(Uuid,)> = sqlx::query_as(
+        "INSERT INTO redirect_uris(client_id, uri) VALUES ($1, $2) \
+         ON CONFLICT (client_id, uri) DO NOTHING RETURNING id",
+    )
+    .bind(client_id)
+    .bind(uri)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(inserted.map(|(id,)| RedirectUriDetails {
+        id,
+        uri: uri.to_string(),
+    }))
+}
+
+/// Checks that authorization codes can safely be sent to a redirect URI.
+///
+/// The URI must be absolute, have a host and no fragment, see
+/// [RFC 6749, section 3.1.2]. It must use `https`, except on loopback hosts,
+/// where native apps may use `http`, see [RFC 8252, section 7.3].
+///
+/// [RFC 6749, section 3.1.2]: https://www.rfc-editor.org/rfc/rfc6749#section-3.1.2
+/// [RFC 8252, section 7.3]: https://www.rfc-editor.org/rfc/rfc8252#section-7.3
+pub fn validate_redirect_uri(uri: &str) -> Result<(), ValidationError> {
+    let invalid = |code: &'static str, message: &'static str| {
+        Err(ValidationError::new(code).with_message(message.into()))
+    };
+
+    if uri.len() > MAX_LEN {
+        return invalid("length", "Redirect URI must be at most 2000 characters.");
+    }
+    let Ok(parsed) = Uri::parse(uri) else {
+        return invalid("redirect_uri", "Redirect URI must be an absolute URI.");
+    };
+    if parsed.has_fragment() {
+        return invalid("redirect_uri", "Redirect URI must not have a fragment.");
+    }
+    let Some(host) = parsed
+        .authority()
+        .map(|authority| authority.host())
+        .filter(|host| !host.is_empty())
+    else {
+        return invalid("redirect_uri", "Redirect URI must have a host.");
+    };
+
+    let scheme = parsed.scheme().as_str();
+    let loopback = LOOPBACK_HOSTS
+        .iter()
+        .any(|loopback| host.eq_ignore_ascii_case(loopback));
+    match scheme.to_ascii_lowercase().as_str() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        _ => invalid(
+            "redirect_uri",
+            "Redirect URI must use https, or http on localhost.",
+        ),
+    }
+}
+
+/// Checks every redirect URI, see [`validate_redirect_uri`].
+pub fn validate_redirect_uris(uris: &[String]) -> Result<(), ValidationError> {
+    uris.iter().try_for_each(|uri| validate_redirect_uri(uri))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_redirect_uri() {
+        assert!(validate_redirect_uri("https://app.lerpz.local/callback").is_ok());
+        assert!(validate_redirect_uri("https://app.lerpz.local/callback?tenant=1").is_ok());
+        assert!(validate_redirect_uri("http://localhost:8080/callback").is_ok());
+        assert!(validate_redirect_uri("http://127.0.0.1/callback").is_ok());
+        assert!(validate_redirect_uri("http://[::1]:3000/").is_ok());
+
+        assert!(validate_redirect_uri("http://app.lerpz.local/callback").is_err());
+        assert!(validate_redirect_uri("https://app.lerpz.local/callback#token").is_err());
+        assert!(validate_redirect_uri("/callback").is_err());
+        assert!(validate_redirect_uri("com.lerpz.app:/callback").is_err());
+        assert!(validate_redirect_uri("javascript:alert(1)").is_err());
+        assert!(validate_redirect_uri("not a uri").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use lerpz_axum::error::{HandlerError, HandlerResult};
+use serde::Deserialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::ClientDetails;
+
+#[derive(Deserialize)]
+pub struct ReplaceScopes {
+    pub scopes: BTreeSet<String>,
+}
+
+/// Replaces the scopes a client is allowed to request.
+///
+/// Tokens already issued to the client keep their scopes until they expire.
+pub async fn handler(
+    State(database): State<PgPool>,
+    Path(client_id): Path<Uuid>,
+    Json(body): Json<ReplaceScopes>,
+) -> HandlerResult<Json<ClientDetails>> {
+    let mut tx = database.begin().await?;
+    super::ensure_exists(&mut *tx, client_id).await?;
+    assign(&mut tx, client_id, &body.scopes).await?;
+    tx.commit().await?;
+
+    Ok(Json(super::client_details(&database, client_id).await?))
+}
+
+/// Replaces the scopes of the client.
+///
+/// Fails with `400 Bad Request` if any of the scopes don't exist.
+pub async fn assign(
+    tx: &mut Transaction<'_, Postgres>,
+    client_id: Uuid,
+    scopes: &BTreeSet<String>,
+) -> HandlerResult<()> {
+    let names: Vec<&str> = scopes.iter().map(String::as_str).collect();
+    let found: Vec<(Uuid, String)> =
+        sqlx::query_as("SELECT id, name FROM scopes WHERE name = ANY($1)")
+            .bind(&names)
+            .fetch_all(&mut **tx)
+            .await?;
+
+    let unknown: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|name| !found.iter().any(|(_, found)| found == name))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(HandlerError::new(
+            StatusCode::BAD_REQUEST,
+            "Unknown scopes",
+            format!("These scopes don't exist: {}.", unknown.join(", ")),
+        ));
+    }
+
+    let ids: Vec<Uuid> = found.into_iter().map(|(id, _)| id).collect();
+    sqlx::query("DELETE FROM client_scopes WHERE client_id = $1 AND NOT (scope_id = ANY($2))")
+        .bind(client_id)
+        .bind(&ids)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO client_scopes(client_id, scope_id) SELECT $1, unnest($2::uuid[]) \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(client_id)
+    .bind(&ids)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use lerpz_axum::error::HandlerResult;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::oauth::secret;
+
+#[derive(Serialize)]
+pub struct SecretResponse {
+    pub client_secret: String,
+    /// When the previous secret stops working, if the client had one.
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+}
+
+/// Issues a new secret for a client, which makes it a confidential client.
+///
+/// The previous secret keeps working for a while, so the client can switch
+/// to the new one without downtime. Any secret older than that is revoked.
+pub async fn handler(
+    State(database): State<PgPool>,
+    Path(client_id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<SecretResponse>)> {
+    let mut tx = database.begin().await?;
+    super::ensure_exists(&mut *tx, client_id).await?;
+
+    let (client_secret, previous_secret_expires_at) = secret::rotate(&mut tx, client_id).await?;
+    tx.commit().await?;
+    tracing::info!(%client_id, "rotated client secret");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SecretResponse {
+            client_secret: client_secret.expose_secret().to_string(),
+            previous_secret_expires_at,
+        }),
+    ))
+}
@@ -0,0 +1,70 @@
+use axum::{Json, extract::State};
+use chrono::{DateTime, Utc};
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::jwt::{AccessToken, Unrevoked},
+};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::tokens::{access, split_scopes};
+
+/// A client the user has authorized.
+#[derive(Serialize, Debug)]
+pub struct AuthorizedClient {
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub client_description: Option<String>,
+    pub scopes: Vec<String>,
+    pub authorized_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A consent joined with its client.
+#[derive(FromRow)]
+struct ConsentRow {
+    client_id: Uuid,
+    client_name: String,
+    client_description: Option<String>,
+    scope: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// Lists the clients the user has consented to, and with which scopes.
+pub async fn handler(
+    State(database): State<PgPool>,
+    Unrevoked(token): Unrevoked<AccessToken>,
+) -> HandlerResult<Json<Vec<AuthorizedClient>>> {
+    // Consents are managed by the user in the portal, so other clients can't
+    // see or revoke them, even with a token for the user.
+    let user_id = access::portal_user(&database, &token)
+        .await?
+        .ok_or_else(HandlerError::forbidden)?
+        .user_id;
+
+    let rows: Vec<ConsentRow> = sqlx::query_as(
+        "SELECT c.id AS client_id, c.name AS client_name, c.description AS client_description, \
+         uc.scope, uc.created_at, uc.updated_at \
+         FROM user_consents uc JOIN oauth_clients c ON c.id = uc.client_id \
+         WHERE uc.user_id = $1 ORDER BY c.name",
+    )
+    .bind(user_id)
+    .fetch_all(&database)
+    .await?;
+
+    let clients = rows
+        .into_iter()
+        .map(|row| AuthorizedClient {
+            client_id: row.client_id,
+            client_name: row.client_name,
+            client_description: row.client_description,
+            scopes: split_scopes(Some(&row.scope)).into_iter().collect(),
+            authorized_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+        .collect();
+
+    Ok(Json(clients))
+}
@@ -0,0 +1,16 @@
+use crate::state::AppState;
+
+use axum::{
+    Router,
+    routing::{delete, get},
+};
+
+mod list;
+mod revoke;
+
+pub fn router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/", get(list::handler))
+        .route("/{client_id}", delete(revoke::handler))
+        .with_state(state)
+}
@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::jwt::{AccessToken, RevocationList, Unrevoked},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    oauth::consent,
+    tokens::{access, refresh},
+};
+
+/// Revokes the consent of the user to a client.
+///
+/// Every refresh token the client has for the user is revoked along with the
+/// access tokens issued from them, so the client has to ask for consent again.
+///
+/// The tokens are revoked before the consent is removed, so that the request
+/// can be retried if revoking them fails.
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(revocations): State<RevocationList>,
+    Unrevoked(token): Unrevoked<AccessToken>,
+    Path(client_id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    // Consents are managed by the user in the portal, so other clients can't
+    // see or revoke them, even with a token for the user.
+    let user_id = access::portal_user(&database, &token)
+        .await?
+        .ok_or_else(HandlerError::forbidden)?
+        .user_id;
+
+    if !consent::exists(&database, user_id, client_id).await? {
+        return Err(HandlerError::new(
+            StatusCode::NOT_FOUND,
+            "Consent not found",
+            "The client hasn't been authorized.",
+        ));
+    }
+
+    let (revoked, revoked_access) =
+        refresh::revoke_client(&database, &revocations, user_id, client_id).await?;
+    consent::revoke(&database, user_id, client_id).await?;
+    tracing::info!(
+        %user_id,
+        %client_id,
+        revoked,
+        revoked_access,
+        "user revoked consent to client"
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
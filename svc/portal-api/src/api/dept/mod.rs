@@ -2,8 +2,13 @@ use crate::state::AppState;
 
 use axum::{
     Router,
+    handler::Handler,
     routing::{get, post},
 };
+use lerpz_axum::middleware::{
+    guard::RequireScope,
+    jwt::{AccessToken, Unrevoked},
+};
 
 mod create;
 mod delete;
@@ -11,14 +16,25 @@ mod list;
 mod read;
 mod update;
 
+/// The token required by the department endpoints.
+type Token = Unrevoked<AccessToken>;
+
 pub fn router(state: AppState) -> Router<AppState> {
+    let read = RequireScope::<Token, _>::new(state.clone(), "dept.read")
+        .with_hierarchy(state.scopes.clone());
+    let write = RequireScope::<Token, _>::new(state.clone(), "dept.write")
+        .with_hierarchy(state.scopes.clone());
+
     Router::new()
-        .route("/", post(create::handler).get(list::handler))
+        .route(
+            "/",
+            post(create::handler.layer(write.clone())).get(list::handler.layer(read.clone())),
+        )
         .route(
             "/{id}",
-            get(read::handler)
-                .put(update::handler)
-                .delete(delete::handler),
+            get(read::handler.layer(read))
+                .put(update::handler.layer(write.clone()))
+                .delete(delete::handler.layer(write)),
         )
         .with_state(state)
 }
@@ -2,10 +2,16 @@ use crate::state::AppState;
 
 use axum::Router;
 
+mod auth;
+mod clients;
+mod consents;
 mod dept;
 
 pub fn router(state: AppState) -> Router<AppState> {
     Router::new()
+        .nest("/auth", auth::router(state.clone()))
+        .nest("/clients", clients::router(state.clone()))
+        .nest("/consents", consents::router(state.clone()))
         .nest("/dept", dept::router(state.clone()))
         .with_state(state)
 }
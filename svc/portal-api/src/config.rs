@@ -22,9 +22,17 @@ enum Env {
 /// once.
 pub static CONFIG: LazyLock<Config> = LazyLock::new(|| Config::from_env().unwrap());
 
+/// Returns the issuer of tokens without a trailing slash.
+pub fn issuer() -> &'static str {
+    CONFIG.ISSUER.trim_end_matches('/')
+}
+
 generate_config!(
     ENV: Env = get_env_parse,
     ADDR: SocketAddr = get_env_parse,
+    ISSUER: String = get_env,
+    AUTHORIZATION_URI: String = get_env,
+    DEVICE_VERIFICATION_URI: String = get_env,
     DATABASE_URL: String = get_env,
     REDIS_URL: String = get_env
 );
@@ -5,13 +5,23 @@ use std::time::Duration;
 
 use axum::Router;
 use bb8_redis::RedisConnectionManager;
-use lerpz_axum::shutdown_signal;
+use lerpz_axum::{
+    middleware::{
+        guard::ScopeHierarchy,
+        jwt::{JwtConfig, RevocationList},
+    },
+    shutdown_signal,
+};
+use lerpz_jwt::VerifierConfig;
 use sqlx::postgres::PgPoolOptions;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
 mod api;
 mod config;
+mod oauth;
 mod state;
+mod tokens;
+mod well_known;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -32,6 +42,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    let peppers = lerpz_pwd::Peppers::from_env()
+        .unwrap_or_else(|err| panic!("can't load password peppers: {err}"));
+    lerpz_pwd::set_peppers(peppers);
+
+    let hashing_pool = lerpz_pwd::HashingPool::from_env()
+        .unwrap_or_else(|err| panic!("can't configure password hashing pool: {err}"));
+    lerpz_pwd::set_hashing_pool(hashing_pool);
+
+    let breaches = lerpz_utils::get_env("PWD_BREACH_FILE")
+        .ok()
+        .map(lerpz_pwd::BreachChecker::open)
+        .transpose()
+        .unwrap_or_else(|err| panic!("can't open breached passwords: {err}"));
+    if breaches.is_none() {
+        tracing::warn!("PWD_BREACH_FILE is not set, passwords won't be checked for breaches");
+    }
+
+    let keys = lerpz_jwt::KeyRing::from_env()
+        .unwrap_or_else(|err| panic!("can't load token signing keys: {err}"));
+    if keys.active().is_none() {
+        panic!("can't sign tokens: JWT_ACTIVE_KID is not set");
+    }
+    let verifier = VerifierConfig::new(config::issuer(), config::issuer());
+
+    let token_hasher = lerpz_pwd::TokenHasher::from_env()
+        .unwrap_or_else(|err| panic!("can't load token hashing key: {err}"));
+
     let database_pool = PgPoolOptions::new()
         .max_connections(5)
         .acquire_timeout(Duration::from_secs(3))
@@ -39,6 +76,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await
         .unwrap_or_else(|err| panic!("can't connect to database: {err}"));
 
+    let admin_secret_file = lerpz_utils::get_env("ADMIN_SECRET_FILE")
+        .ok()
+        .filter(|path| !path.is_empty());
+    if let Some(path) = admin_secret_file {
+        let issued =
+            oauth::secret::bootstrap(&database_pool, tokens::ADMIN_CLIENT_ID, path.as_ref())
+                .await
+                .unwrap_or_else(|err| panic!("can't issue admin client secret: {err}"));
+        if issued {
+            tracing::info!("wrote the secret of the admin client to {path}");
+        }
+    }
+    let admin_has_secret = oauth::secret::is_confidential(&database_pool, tokens::ADMIN_CLIENT_ID)
+        .await
+        .unwrap_or_else(|err| panic!("can't check admin client secret: {err}"));
+    if !admin_has_secret {
+        panic!("the admin client has no secret, set ADMIN_SECRET_FILE to issue one");
+    }
+
     let manager = RedisConnectionManager::new(CONFIG.REDIS_URL.clone())
         .unwrap_or_else(|err| panic!("can't connect to redis: {err}"));
     let redis_pool = bb8::Pool::builder()
@@ -46,13 +102,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await
         .unwrap_or_else(|err| panic!("can't create redis pool: {err}"));
 
+    let revocations = RevocationList::new(redis_pool.clone(), database_pool.clone());
+    match revocations.restore().await {
+        Ok(count) => tracing::info!("restored {count} revoked access tokens"),
+        Err(err) => tracing::warn!("can't restore revoked access tokens: {err}"),
+    }
+
+    let scopes: Vec<(String, String)> = sqlx::query_as(
+        "SELECT child.name, parent.name FROM scopes child \
+         JOIN scopes parent ON child.parent_scope_id = parent.id",
+    )
+    .fetch_all(&database_pool)
+    .await
+    .unwrap_or_else(|err| panic!("can't load scope hierarchy: {err}"));
+
     let state = AppState {
         database: database_pool,
         redis: redis_pool,
+        breaches,
+        jwt: JwtConfig::new(keys, verifier),
+        revocations,
+        token_hasher,
+        scopes: ScopeHierarchy::from_pairs(scopes),
     };
 
     let app = Router::new()
         .nest("/api", api::router(state.clone()))
+        .nest("/oauth", oauth::router(state.clone()))
+        .nest("/.well-known", well_known::router(state.clone()))
         .with_state(state);
 
     let listener = tokio::net::TcpListener::bind(&CONFIG.ADDR).await?;
@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+
+use axum::{Json, extract::State};
+use bb8_redis::RedisConnectionManager;
+use lerpz_axum::middleware::{
+    guard::ScopeHierarchy,
+    jwt::{AccessToken, Unrevoked},
+};
+use lerpz_pwd::TokenHasher;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    client,
+    code::AuthorizationCode,
+    consent,
+    error::{ErrorCode, OAuthError, OAuthResult},
+    pkce,
+};
+use crate::tokens::access;
+
+/// The parameters of an authorization request.
+///
+/// See [RFC 6749, section 4.1.1](https://www.rfc-editor.org/rfc/rfc6749#section-4.1.1).
+#[derive(Deserialize, Debug)]
+pub struct AuthorizeRequest {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+    /// Whether the user consented to the request, once they were asked.
+    pub consent: Option<bool>,
+}
+
+/// What the portal should do next.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum AuthorizeResponse {
+    /// Send the user to the URI.
+    Redirect { redirect_to: String },
+    /// Ask the user to consent, and send the request again with `consent`.
+    Consent { consent_required: ConsentPrompt },
+}
+
+/// What the user is asked to consent to.
+#[derive(Serialize, Debug)]
+pub struct ConsentPrompt {
+    pub client_id: Uuid,
+    pub client_name: String,
+    /// Every scope the client will get.
+    pub scopes: BTreeSet<String>,
+    /// The scopes the user hasn't consented to before.
+    pub new_scopes: BTreeSet<String>,
+}
+
+/// Authorizes a client to act on behalf of the signed in user.
+///
+/// The portal calls this with the token of the user, and sends the user to
+/// `redirect_to`, which carries either the authorization code or an error for
+/// the client.
+///
+/// If the client requests scopes the user hasn't consented to before, the
+/// portal gets `consent_required` instead, and must ask the user and send the
+/// request again with `consent` set to their answer.
+///
+/// Errors about the client or the redirect URI are returned directly, since
+/// the redirect URI can't be trusted until both are known to be valid.
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(redis): State<bb8::Pool<RedisConnectionManager>>,
+    State(hasher): State<TokenHasher>,
+    State(hierarchy): State<ScopeHierarchy>,
+    Unrevoked(token): Unrevoked<AccessToken>,
+    Json(request): Json<AuthorizeRequest>,
+) -> OAuthResult<Json<AuthorizeResponse>> {
+    let user = access::portal_user(&database, &token)
+        .await?
+        .ok_or_else(portal_only)?;
+
+    let client_id = request
+        .client_id
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("The client_id parameter is missing."))?;
+    let client = client::find(&database, client_id)
+        .await?
+        .ok_or_else(|| OAuthError::invalid_request("The client is unknown."))?;
+
+    let registered = client::redirect_uris(&database, client.id).await?;
+    let redirect_uri = match (&request.redirect_uri, registered.as_slice()) {
+        (Some(given), _) if registered.contains(given) => given.clone(),
+        (None, [only]) => only.clone(),
+        (Some(_), _) => {
+            return Err(OAuthError::invalid_request(
+                "The redirect_uri is not registered for the client.",
+            ));
+        }
+        (None, _) => {
+            return Err(OAuthError::invalid_request(
+                "The redirect_uri parameter is missing.",
+            ));
+        }
+    };
+
+    let redirect = Redirect {
+        uri: &redirect_uri,
+        state: request.state.as_deref(),
+    };
+
+    if request.response_type.as_deref() != Some("code") {
+        return Ok(redirect.error(OAuthError::new(
+            ErrorCode::UnsupportedResponseType,
+            "Only the \"code\" response type is supported.",
+        )));
+    }
+
+    let Some(code_challenge) = request.code_challenge.filter(|c| pkce::is_well_formed(c)) else {
+        return Ok(redirect.error(OAuthError::invalid_request(
+            "A valid code_challenge is required.",
+        )));
+    };
+    if request.code_challenge_method.as_deref() != Some(pkce::METHOD) {
+        return Ok(redirect.error(OAuthError::invalid_request(
+            "The code_challenge_method must be \"S256\".",
+        )));
+    }
+
+    let allowed = client::delegable(
+        crate::tokens::client_scopes(&database, client.id).await?,
+        &hierarchy,
+    );
+    let scopes = match client::select_scopes(request.scope.as_deref(), &allowed, &hierarchy) {
+        Ok(scopes) => scopes,
+        Err(err) => return Ok(redirect.error(err)),
+    };
+
+    if request.consent == Some(false) {
+        return Ok(redirect.error(OAuthError::new(
+            ErrorCode::AccessDenied,
+            "The user denied the request.",
+        )));
+    }
+    let consented = consent::granted(&database, user.user_id, client.id).await?;
+    if !scopes.is_subset(&consented) {
+        if request.consent != Some(true) {
+            return Ok(Json(AuthorizeResponse::Consent {
+                consent_required: ConsentPrompt {
+                    client_id: client.id,
+                    client_name: client.name,
+                    new_scopes: scopes.difference(&consented).cloned().collect(),
+                    scopes,
+                },
+            }));
+        }
+        consent::grant(&database, user.user_id, client.id, &scopes).await?;
+    }
+
+    let code = AuthorizationCode {
+        client_id: client.id,
+        user_id: user.user_id,
+        redirect_uri_given: request.redirect_uri.is_some(),
+        redirect_uri: redirect_uri.clone(),
+        scopes,
+        code_challenge,
+        nonce: request.nonce,
+        auth_time: user.auth_time,
+        amr: user.amr,
+    }
+    .store(&redis, &hasher)
+    .await?;
+
+    Ok(redirect.with(&[("code", code.expose_secret())]))
+}
+
+/// Error returned when a token wasn't issued to the portal.
+///
+/// Only the portal may act on behalf of the user towards other clients.
+pub fn portal_only() -> OAuthError {
+    OAuthError::new(
+        ErrorCode::AccessDenied,
+        "Only the portal can authorize clients.",
+    )
+}
+
+/// A redirect back to the client.
+struct Redirect<'a> {
+    uri: &'a str,
+    state: Option<&'a str>,
+}
+
+impl Redirect<'_> {
+    /// Redirects with the parameters, and the state if one was given.
+    fn with(&self, params: &[(&str, &str)]) -> Json<AuthorizeResponse> {
+        let mut params = params.to_vec();
+        if let Some(state) = self.state {
+            params.push(("state", state));
+        }
+
+        let query = serde_urlencoded::to_string(params).expect("strings can be url encoded");
+        let separator = if self.uri.contains('?') { '&' } else { '?' };
+        Json(AuthorizeResponse::Redirect {
+            redirect_to: format!("{}{separator}{query}", self.uri),
+        })
+    }
+
+    /// Redirects with the error.
+    fn error(&self, err: OAuthError) -> Json<AuthorizeResponse> {
+        let code = serde_json::to_value(err.code()).expect("error codes can be serialized");
+        let code = code.as_str().expect("error codes are strings");
+        match err.description() {
+            Some(description) => self.with(&[("error", code), ("error_description", description)]),
+            None => self.with(&[("error", code)]),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Looking up OAuth clients and what they are allowed to do.
+
+use std::collections::BTreeSet;
+
+use axum::http::{HeaderMap, header::AUTHORIZATION};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use lerpz_axum::middleware::guard::{Permissions, ScopeHierarchy};
+use lerpz_model::OAuthClient;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use super::{
+    error::{ErrorCode, OAuthError, OAuthResult},
+    secret,
+};
+use crate::tokens::{ADMIN_CLIENT_ID, split_scopes};
+
+/// A client that has authenticated, or identified itself if it is public.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedClient {
+    pub client: OAuthClient,
+    /// Whether the client has secrets and authenticated with one.
+    pub confidential: bool,
+}
+
+/// Authenticates the client making a request to the token endpoint.
+///
+/// Supports `client_secret_basic`, `client_secret_post` and `none`, see
+/// [RFC 6749, section 2.3.1]. Only one method may be used per request. A
+/// client that was ever issued a secret must use one of its secrets, while a
+/// public client must not send a secret at all. The admin client is always
+/// treated as confidential.
+///
+/// [RFC 6749, section 2.3.1]: https://www.rfc-editor.org/rfc/rfc6749#section-2.3.1
+pub async fn authenticate(
+    database: &PgPool,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> OAuthResult<AuthenticatedClient> {
+    let (client_id, client_secret) = match basic_credentials(headers)? {
+        Some(_) if client_secret.is_some() => {
+            return Err(OAuthError::invalid_request(
+                "Only one client authentication method can be used.",
+            ));
+        }
+        Some((basic_id, _)) if client_id.is_some_and(|id| id != basic_id) => {
+            return Err(OAuthError::invalid_request(
+                "The client_id doesn't match the authorization header.",
+            ));
+        }
+        Some((id, secret)) => (id, Some(secret)),
+        None => (
+            client_id
+                .ok_or_else(OAuthError::invalid_client)?
+                .to_string(),
+            client_secret.map(String::from),
+        ),
+    };
+
+    let client = find(database, &client_id)
+        .await?
+        .ok_or_else(OAuthError::invalid_client)?;
+    // The admin client can manage every other client, so it must never be
+    // used without a secret, not even before its first secret was issued.
+    let confidential =
+        client.id == ADMIN_CLIENT_ID || secret::is_confidential(database, client.id).await?;
+
+    match (client_secret, confidential) {
+        (Some(client_secret), true) => {
+            if !secret::verify(database, client.id, &client_secret).await? {
+                return Err(OAuthError::invalid_client());
+            }
+        }
+        (None, false) => {}
+        _ => return Err(OAuthError::invalid_client()),
+    }
+
+    Ok(AuthenticatedClient {
+        client,
+        confidential,
+    })
+}
+
+/// Reads the client credentials from a `Basic` authorization header.
+///
+/// The id and secret are form encoded before they are joined, so they are
+/// decoded after splitting them.
+fn basic_credentials(headers: &HeaderMap) -> OAuthResult<Option<(String, String)>> {
+    let Some(credentials) = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+    else {
+        return Ok(None);
+    };
+
+    let credentials = STANDARD
+        .decode(credentials.trim())
+        .ok()
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .ok_or_else(OAuthError::invalid_client)?;
+    let (id, secret) = credentials
+        .split_once(':')
+        .ok_or_else(OAuthError::invalid_client)?;
+
+    match (form_decode(id), form_decode(secret)) {
+        (Some(id), Some(secret)) => Ok(Some((id, secret))),
+        _ => Err(OAuthError::invalid_client()),
+    }
+}
+
+/// Decodes a single `application/x-www-form-urlencoded` value.
+fn form_decode(value: &str) -> Option<String> {
+    let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&format!("v={value}")).ok()?;
+    match decoded.as_slice() {
+        [(_, value)] => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// Returns the client with the given id.
+///
+/// Returns [`None`] if the id isn't a valid UUID or no such client exists.
+pub async fn find(
+    executor: impl PgExecutor<'_>,
+    client_id: &str,
+) -> OAuthResult<Option<OAuthClient>> {
+    let Ok(client_id) = client_id.parse::<Uuid>() else {
+        return Ok(None);
+    };
+
+    Ok(sqlx::query_as("SELECT * FROM oauth_clients WHERE id = $1")
+        .bind(client_id)
+        .fetch_optional(executor)
+        .await?)
+}
+
+/// Returns the redirect URIs registered for the client.
+pub async fn redirect_uris(
+    executor: impl PgExecutor<'_>,
+    client_id: Uuid,
+) -> sqlx::Result<Vec<String>> {
+    let uris: Vec<(String,)> = sqlx::query_as("SELECT uri FROM redirect_uris WHERE client_id = $1")
+        .bind(client_id)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(uris.into_iter().map(|(uri,)| uri).collect())
+}
+
+/// Scopes that are only granted to clients through the `client_credentials`
+/// grant, along with their children.
+///
+/// They let a client manage other clients, which a user must never be able to
+/// delegate to a client by approving it.
+pub const CLIENT_ONLY_SCOPES: [&str; 1] = ["clients"];
+
+/// Removes the scopes that can't be delegated by a user.
+///
+/// Used for grants that issue tokens on behalf of a user, so that scopes in
+/// [`CLIENT_ONLY_SCOPES`] can't be requested through them.
+pub fn delegable(allowed: BTreeSet<String>, hierarchy: &ScopeHierarchy) -> BTreeSet<String> {
+    let client_only = CLIENT_ONLY_SCOPES.map(String::from).into();
+    allowed
+        .into_iter()
+        .filter(|scope| !grants(&client_only, scope, hierarchy))
+        .collect()
+}
+
+/// Selects the requested scopes that are allowed.
+///
+/// Requesting no scopes selects every allowed scope. A scope is also allowed
+/// if one of its parents in the hierarchy is. Scopes that aren't allowed are
+/// left out, but if none of the requested scopes are allowed the request
+/// fails with `invalid_scope`.
+pub fn select_scopes(
+    requested: Option<&str>,
+    allowed: &BTreeSet<String>,
+    hierarchy: &ScopeHierarchy,
+) -> OAuthResult<BTreeSet<String>> {
+    let requested = split_scopes(requested);
+    if requested.is_empty() {
+        return Ok(allowed.clone());
+    }
+
+    let selected: BTreeSet<_> = requested
+        .into_iter()
+        .filter(|scope| grants(allowed, scope, hierarchy))
+        .collect();
+    if selected.is_empty() {
+        return Err(OAuthError::new(
+            ErrorCode::InvalidScope,
+            "None of the requested scopes are allowed for the client.",
+        ));
+    }
+    Ok(selected)
+}
+
+/// Whether the scopes grant the scope, either directly or through one of its
+/// parents in the hierarchy.
+pub fn grants(scopes: &BTreeSet<String>, scope: &str, hierarchy: &ScopeHierarchy) -> bool {
+    hierarchy.grants(&GrantedScopes(scopes), scope)
+}
+
+/// Scopes granted to a client, which can be checked against the hierarchy
+/// like a token.
+struct GrantedScopes<'a>(&'a BTreeSet<String>);
+
+impl Permissions for GrantedScopes<'_> {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.0.contains(scope)
+    }
+
+    fn has_role(&self, _: &str) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_scopes() {
+        let allowed = BTreeSet::from(["dept".to_string(), "profile".to_string()]);
+        let hierarchy = ScopeHierarchy::from_pairs([("dept.read", "dept")]);
+
+        assert_eq!(select_scopes(None, &allowed, &hierarchy).unwrap(), allowed);
+        assert_eq!(
+            select_scopes(Some("dept admin"), &allowed, &hierarchy).unwrap(),
+            BTreeSet::from(["dept".to_string()])
+        );
+        assert_eq!(
+            select_scopes(Some("dept.read profile.read"), &allowed, &hierarchy).unwrap(),
+            BTreeSet::from(["dept.read".to_string()])
+        );
+        assert_eq!(
+            select_scopes(Some("admin"), &allowed, &hierarchy)
+                .unwrap_err()
+                .code(),
+            ErrorCode::InvalidScope
+        );
+    }
+
+    #[test]
+    fn test_delegable() {
+        let allowed = BTreeSet::from([
+            "clients".to_string(),
+            "clients.write".to_string(),
+            "dept".to_string(),
+        ]);
+        let hierarchy = ScopeHierarchy::from_pairs([("clients.write", "clients")]);
+
+        let delegable = delegable(allowed, &hierarchy);
+        assert_eq!(delegable, BTreeSet::from(["dept".to_string()]));
+        assert_eq!(
+            select_scopes(Some("clients.write"), &delegable, &hierarchy)
+                .unwrap_err()
+                .code(),
+            ErrorCode::InvalidScope
+        );
+    }
+
+    #[test]
+    fn test_basic_credentials() {
+        let mut headers = HeaderMap::new();
+        assert!(basic_credentials(&headers).unwrap().is_none());
+
+        let encoded = STANDARD.encode("client%3Aid:s%2Bcret");
+        headers.insert(AUTHORIZATION, format!("Basic {encoded}").parse().unwrap());
+        assert_eq!(
+            basic_credentials(&headers).unwrap(),
+            Some(("client:id".to_string(), "s+cret".to_string()))
+        );
+
+        headers.insert(AUTHORIZATION, "Basic not base64!".parse().unwrap());
+        assert_eq!(
+            basic_credentials(&headers).unwrap_err().code(),
+            ErrorCode::InvalidClient
+        );
+    }
+}
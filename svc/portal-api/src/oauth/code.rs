@@ -0,0 +1,84 @@
+//! Single use authorization codes stored in Redis.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use bb8_redis::RedisConnectionManager;
+use lerpz_pwd::{SecretString, TokenHasher, generate_token};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The prefix of authorization codes.
+pub const PREFIX: &str = "lrpz_ac";
+
+/// How long an authorization code can be redeemed after it was issued.
+pub const LIFETIME: Duration = Duration::from_secs(60);
+
+/// Prefix of the Redis keys holding authorization codes.
+const REDIS_PREFIX: &str = "oauth:code:";
+
+/// What an authorization code was issued for.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthorizationCode {
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    /// The redirect URI the code was sent to.
+    pub redirect_uri: String,
+    /// Whether the redirect URI was given in the authorization request, in
+    /// which case the token request must give the same one.
+    pub redirect_uri_given: bool,
+    pub scopes: BTreeSet<String>,
+    pub code_challenge: String,
+    /// The OpenID Connect nonce, to be put in the ID token.
+    pub nonce: Option<String>,
+    /// When the user authenticated, as a Unix timestamp.
+    pub auth_time: i64,
+    /// How the user authenticated.
+    pub amr: Vec<String>,
+}
+
+impl AuthorizationCode {
+    /// Stores the code in Redis and returns it.
+    ///
+    /// Only the keyed hash of the code is used as the key, so codes can't be
+    /// read back from Redis.
+    pub async fn store(
+        &self,
+        redis: &bb8::Pool<RedisConnectionManager>,
+        hasher: &TokenHasher,
+    ) -> anyhow::Result<SecretString> {
+        let code = generate_token(PREFIX)?;
+
+        let mut conn = redis.get().await?;
+        redis::cmd("SET")
+            .arg(format!(
+                "{REDIS_PREFIX}{}",
+                hasher.hash(code.expose_secret())
+            ))
+            .arg(serde_json::to_string(self)?)
+            .arg("EX")
+            .arg(LIFETIME.as_secs())
+            .query_async::<()>(&mut *conn)
+            .await?;
+
+        Ok(code)
+    }
+
+    /// Takes the code out of Redis, so it can't be redeemed again.
+    ///
+    /// Returns [`None`] if the code doesn't exist or has expired.
+    pub async fn take(
+        redis: &bb8::Pool<RedisConnectionManager>,
+        hasher: &TokenHasher,
+        code: &str,
+    ) -> anyhow::Result<Option<Self>> {
+        let mut conn = redis.get().await?;
+        let value: Option<String> = redis::cmd("GETDEL")
+            .arg(format!("{REDIS_PREFIX}{}", hasher.hash(code)))
+            .query_async(&mut *conn)
+            .await?;
+
+        Ok(value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()?)
+    }
+}
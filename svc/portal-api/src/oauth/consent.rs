@@ -0,0 +1,87 @@
+//! The scopes users have consented to give clients.
+
+use std::collections::BTreeSet;
+
+use lerpz_model::UserConsent;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::tokens::{join_scopes, split_scopes};
+
+/// Returns the scopes the user has consented to give the client.
+pub async fn granted(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    client_id: Uuid,
+) -> sqlx::Result<BTreeSet<String>> {
+    let consent: Option<UserConsent> =
+        sqlx::query_as("SELECT * FROM user_consents WHERE user_id = $1 AND client_id = $2")
+            .bind(user_id)
+            .bind(client_id)
+            .fetch_optional(executor)
+            .await?;
+
+    Ok(split_scopes(
+        consent.as_ref().map(|consent| consent.scope.as_str()),
+    ))
+}
+
+/// Whether the user has consented to the client.
+pub async fn exists(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    client_id: Uuid,
+) -> sqlx::Result<bool> {
+    sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM user_consents WHERE user_id = $1 AND client_id = $2)",
+    )
+    .bind(user_id)
+    .bind(client_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Records that the user consented to give the client the scopes.
+///
+/// The scopes are added to the ones consented to before.
+pub async fn grant(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    client_id: Uuid,
+    scopes: &BTreeSet<String>,
+) -> sqlx::Result<()> {
+    let scope = join_scopes(scopes).unwrap_or_default();
+
+    sqlx::query(
+        "INSERT INTO user_consents(user_id, client_id, scope) VALUES ($1, $2, $3) \
+         ON CONFLICT (user_id, client_id) DO UPDATE SET scope = ( \
+             SELECT COALESCE(string_agg(DISTINCT s, ' ' ORDER BY s), '') \
+             FROM unnest(string_to_array(user_consents.scope || ' ' || EXCLUDED.scope, ' ')) s \
+             WHERE s <> '' \
+         )",
+    )
+    .bind(user_id)
+    .bind(client_id)
+    .bind(scope)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes the consent of the user to the client.
+///
+/// Returns `false` if the user never consented to the client.
+pub async fn revoke(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    client_id: Uuid,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query("DELETE FROM user_consents WHERE user_id = $1 AND client_id = $2")
+        .bind(user_id)
+        .bind(client_id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,279 @@
+//! Device authorization grants stored in Redis.
+//!
+//! A grant is stored under the keyed hash of its device code, which the
+//! device polls the token endpoint with. The user code the user types in on
+//! the verification page points to the same grant.
+
+use std::{collections::BTreeSet, sync::LazyLock, time::Duration};
+
+use bb8_redis::RedisConnectionManager;
+use lerpz_pwd::{SecretString, TokenHasher, generate_token};
+use rand::Rng;
+use redis::Script;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The prefix of device codes.
+pub const PREFIX: &str = "lrpz_dc";
+
+/// How long the user has to approve a device.
+pub const LIFETIME: Duration = Duration::from_secs(600);
+
+/// How many seconds a device should wait between polls to begin with.
+pub const INTERVAL: u64 = 5;
+
+/// How many seconds the interval grows when a device polls too often.
+pub const SLOW_DOWN: u64 = 5;
+
+/// Prefix of the Redis keys holding device grants.
+const REDIS_PREFIX: &str = "oauth:device:";
+
+/// Prefix of the Redis keys pointing from a user code to a device grant.
+const REDIS_USER_CODE_PREFIX: &str = "oauth:device:user:";
+
+/// Replaces a stored grant, but only if it is still pending.
+static SAVE_IF_PENDING: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local current = redis.call('GET', KEYS[1])
+        if not current or cjson.decode(current).status ~= 'pending' then
+            return 0
+        end
+        redis.call('SET', KEYS[1], ARGV[1], 'KEEPTTL')
+        return 1
+        ",
+    )
+});
+
+/// The characters user codes are made of.
+///
+/// Only consonants without look-alikes are used, so codes are easy to type
+/// and can't spell words, see [RFC 8628, section 6.1].
+///
+/// [RFC 8628, section 6.1]: https://www.rfc-editor.org/rfc/rfc8628#section-6.1
+const USER_CODE_CHARS: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Amount of characters in a user code.
+const USER_CODE_LEN: usize = 8;
+
+/// Whether the user has acted on a device grant yet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeviceStatus {
+    Pending,
+    Approved {
+        user_id: Uuid,
+        /// When the user authenticated, as a Unix timestamp.
+        auth_time: i64,
+        /// How the user authenticated.
+        amr: Vec<String>,
+    },
+    Denied,
+}
+
+/// What a device code was issued for.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceGrant {
+    pub client_id: Uuid,
+    pub scopes: BTreeSet<String>,
+    /// The user code, without formatting.
+    pub user_code: String,
+    /// How many seconds the device must wait between polls.
+    pub interval: u64,
+    /// When the device last polled, as a Unix timestamp.
+    pub last_polled_at: Option<i64>,
+    #[serde(flatten)]
+    pub status: DeviceStatus,
+}
+
+/// A device grant along with the key it is stored under.
+#[derive(Debug, Clone)]
+pub struct StoredGrant {
+    key: String,
+    pub grant: DeviceGrant,
+}
+
+impl DeviceGrant {
+    /// Creates a pending grant with a new user code.
+    pub fn new(client_id: Uuid, scopes: BTreeSet<String>) -> Self {
+        Self {
+            client_id,
+            scopes,
+            user_code: generate_user_code(),
+            interval: INTERVAL,
+            last_polled_at: None,
+            status: DeviceStatus::Pending,
+        }
+    }
+
+    /// Stores the grant in Redis and returns its device code.
+    ///
+    /// A new user code is picked if the current one is already in use.
+    pub async fn store(
+        &mut self,
+        redis: &bb8::Pool<RedisConnectionManager>,
+        hasher: &TokenHasher,
+    ) -> anyhow::Result<SecretString> {
+        let device_code = generate_token(PREFIX)?;
+        let key = format!("{REDIS_PREFIX}{}", hasher.hash(device_code.expose_secret()));
+
+        let mut conn = redis.get().await?;
+        loop {
+            let claimed: Option<String> = redis::cmd("SET")
+                .arg(format!("{REDIS_USER_CODE_PREFIX}{}", self.user_code))
+                .arg(&key)
+                .arg("NX")
+                .arg("EX")
+                .arg(LIFETIME.as_secs())
+                .query_async(&mut *conn)
+                .await?;
+            if claimed.is_some() {
+                break;
+            }
+            self.user_code = generate_user_code();
+        }
+
+        redis::cmd("SET")
+            .arg(&key)
+            .arg(serde_json::to_string(self)?)
+            .arg("EX")
+            .arg(LIFETIME.as_secs())
+            .query_async::<()>(&mut *conn)
+            .await?;
+
+        Ok(device_code)
+    }
+
+    /// Returns the grant of a device code.
+    pub async fn find(
+        redis: &bb8::Pool<RedisConnectionManager>,
+        hasher: &TokenHasher,
+        device_code: &str,
+    ) -> anyhow::Result<Option<StoredGrant>> {
+        let key = format!("{REDIS_PREFIX}{}", hasher.hash(device_code));
+        Self::load(redis, key).await
+    }
+
+    /// Returns the grant of a user code, as typed in by the user.
+    pub async fn find_by_user_code(
+        redis: &bb8::Pool<RedisConnectionManager>,
+        user_code: &str,
+    ) -> anyhow::Result<Option<StoredGrant>> {
+        let Some(user_code) = normalize_user_code(user_code) else {
+            return Ok(None);
+        };
+
+        let mut conn = redis.get().await?;
+        let key: Option<String> = redis::cmd("GET")
+            .arg(format!("{REDIS_USER_CODE_PREFIX}{user_code}"))
+            .query_async(&mut *conn)
+            .await?;
+        drop(conn);
+
+        match key {
+            Some(key) => Self::load(redis, key).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn load(
+        redis: &bb8::Pool<RedisConnectionManager>,
+        key: String,
+    ) -> anyhow::Result<Option<StoredGrant>> {
+        let mut conn = redis.get().await?;
+        let value: Option<String> = redis::cmd("GET").arg(&key).query_async(&mut *conn).await?;
+
+        Ok(value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()?
+            .map(|grant| StoredGrant { key, grant }))
+    }
+}
+
+impl StoredGrant {
+    /// Writes the changes to the grant back to Redis, unless it was decided
+    /// on since it was loaded.
+    ///
+    /// Both the device polling and the user deciding change pending grants,
+    /// so the write only happens if the stored grant is still pending. This
+    /// keeps a poll from overwriting the decision of the user. Returns
+    /// `false` if the grant wasn't written.
+    ///
+    /// The grant keeps expiring at the same time.
+    pub async fn save_if_pending(
+        &self,
+        redis: &bb8::Pool<RedisConnectionManager>,
+    ) -> anyhow::Result<bool> {
+        let mut conn = redis.get().await?;
+        let saved: bool = SAVE_IF_PENDING
+            .key(&self.key)
+            .arg(serde_json::to_string(&self.grant)?)
+            .invoke_async(&mut *conn)
+            .await?;
+        Ok(saved)
+    }
+
+    /// Removes the grant, so the device code can't be used again.
+    ///
+    /// Returns `false` if it was already removed, in which case the grant must
+    /// not be redeemed.
+    pub async fn remove(&self, redis: &bb8::Pool<RedisConnectionManager>) -> anyhow::Result<bool> {
+        let mut conn = redis.get().await?;
+        let removed: u64 = redis::cmd("DEL")
+            .arg(&self.key)
+            .arg(format!("{REDIS_USER_CODE_PREFIX}{}", self.grant.user_code))
+            .query_async(&mut *conn)
+            .await?;
+        Ok(removed > 0)
+    }
+}
+
+/// Generates a random user code.
+fn generate_user_code() -> String {
+    let mut rng = rand::rng();
+    (0..USER_CODE_LEN)
+        .map(|_| USER_CODE_CHARS[rng.random_range(0..USER_CODE_CHARS.len())] as char)
+        .collect()
+}
+
+/// Formats a user code to be shown to the user, e.g. `BCDF-GHJK`.
+pub fn format_user_code(user_code: &str) -> String {
+    let (first, second) = user_code.split_at(user_code.len() / 2);
+    format!("{first}-{second}")
+}
+
+/// Normalizes a user code typed in by the user.
+///
+/// Case, dashes and spaces are ignored. Returns [`None`] if it can't be a
+/// user code.
+pub fn normalize_user_code(user_code: &str) -> Option<String> {
+    let user_code: String = user_code
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    (user_code.len() == USER_CODE_LEN && user_code.bytes().all(|b| USER_CODE_CHARS.contains(&b)))
+        .then_some(user_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_code() {
+        let user_code = generate_user_code();
+        let formatted = format_user_code(&user_code);
+        assert_eq!(formatted.len(), USER_CODE_LEN + 1);
+        assert_eq!(normalize_user_code(&formatted), Some(user_code.clone()));
+        assert_eq!(
+            normalize_user_code(&formatted.to_lowercase()),
+            Some(user_code)
+        );
+
+        assert_eq!(normalize_user_code("bcdf ghjk"), Some("BCDFGHJK".into()));
+        assert_eq!(normalize_user_code("ABCD-EFGH"), None);
+        assert_eq!(normalize_user_code("BCDF"), None);
+    }
+}
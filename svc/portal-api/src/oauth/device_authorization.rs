@@ -0,0 +1,92 @@
+use axum::{
+    Form, Json,
+    extract::{State, rejection::FormRejection},
+    http::{HeaderMap, header::CACHE_CONTROL},
+    response::IntoResponse,
+};
+use bb8_redis::RedisConnectionManager;
+use lerpz_axum::middleware::guard::ScopeHierarchy;
+use lerpz_pwd::TokenHasher;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use super::{
+    client,
+    device::{self, DeviceGrant},
+    error::{OAuthError, OAuthResult},
+};
+use crate::{config::CONFIG, tokens::client_scopes};
+
+/// The parameters of a device authorization request.
+#[derive(Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// What the device should show the user, and how it should poll.
+///
+/// See [RFC 8628, section 3.2](https://www.rfc-editor.org/rfc/rfc8628#section-3.2).
+#[derive(Serialize, Debug)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+/// Starts the device authorization grant for a device that can't open a
+/// browser itself.
+///
+/// The user approves the device in the portal by typing in the user code,
+/// while the device polls the token endpoint with the device code.
+///
+/// See [RFC 8628](https://www.rfc-editor.org/rfc/rfc8628).
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(redis): State<bb8::Pool<RedisConnectionManager>>,
+    State(hasher): State<TokenHasher>,
+    State(hierarchy): State<ScopeHierarchy>,
+    headers: HeaderMap,
+    form: Result<Form<DeviceAuthorizationRequest>, FormRejection>,
+) -> OAuthResult<impl IntoResponse> {
+    let Form(request) = form.map_err(|err| OAuthError::invalid_request(err.body_text()))?;
+
+    let client = client::authenticate(
+        &database,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?
+    .client;
+
+    let allowed = client::delegable(client_scopes(&database, client.id).await?, &hierarchy);
+    let scopes = client::select_scopes(request.scope.as_deref(), &allowed, &hierarchy)?;
+
+    let mut grant = DeviceGrant::new(client.id, scopes);
+    let device_code = grant.store(&redis, &hasher).await?;
+
+    let verification_uri = CONFIG.DEVICE_VERIFICATION_URI.clone();
+    let user_code = device::format_user_code(&grant.user_code);
+    let query = serde_urlencoded::to_string([("user_code", &user_code)])?;
+    let separator = if verification_uri.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+
+    let response = DeviceAuthorizationResponse {
+        device_code: device_code.expose_secret().to_string(),
+        verification_uri_complete: format!("{verification_uri}{separator}{query}"),
+        verification_uri,
+        user_code,
+        expires_in: device::LIFETIME.as_secs(),
+        interval: grant.interval,
+    };
+
+    Ok(([(CACHE_CONTROL, "no-store")], Json(response)))
+}
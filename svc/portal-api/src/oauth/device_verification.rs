@@ -0,0 +1,132 @@
+//! The API behind the page in the portal where users approve devices.
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use bb8_redis::RedisConnectionManager;
+use lerpz_axum::middleware::jwt::{AccessToken, Unrevoked};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    authorize::portal_only,
+    client, consent,
+    device::{self, DeviceGrant, DeviceStatus, StoredGrant},
+    error::{OAuthError, OAuthResult},
+};
+use crate::tokens::access;
+
+#[derive(Deserialize)]
+pub struct DeviceQuery {
+    pub user_code: String,
+}
+
+/// What the user is asked to approve.
+#[derive(Serialize, Debug)]
+pub struct DeviceDetails {
+    pub user_code: String,
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeviceDecision {
+    pub user_code: String,
+    pub approve: bool,
+}
+
+/// Returns the client and scopes of a pending device grant, so the user can
+/// tell whether it is the device in front of them.
+pub async fn details(
+    State(database): State<PgPool>,
+    State(redis): State<bb8::Pool<RedisConnectionManager>>,
+    Unrevoked(token): Unrevoked<AccessToken>,
+    Query(query): Query<DeviceQuery>,
+) -> OAuthResult<Json<DeviceDetails>> {
+    access::portal_user(&database, &token)
+        .await?
+        .ok_or_else(portal_only)?;
+    let stored = pending(&redis, &query.user_code).await?;
+
+    let client = client::find(&database, &stored.grant.client_id.to_string())
+        .await?
+        .ok_or_else(invalid_user_code)?;
+
+    Ok(Json(DeviceDetails {
+        user_code: device::format_user_code(&stored.grant.user_code),
+        client_id: client.id,
+        client_name: client.name,
+        scopes: stored.grant.scopes.into_iter().collect(),
+    }))
+}
+
+/// Approves or denies a pending device grant on behalf of the user.
+///
+/// Approving counts as consenting to the scopes of the grant. The device
+/// finds out the next time it polls the token endpoint.
+pub async fn decide(
+    State(database): State<PgPool>,
+    State(redis): State<bb8::Pool<RedisConnectionManager>>,
+    Unrevoked(token): Unrevoked<AccessToken>,
+    Json(decision): Json<DeviceDecision>,
+) -> OAuthResult<StatusCode> {
+    let user = access::portal_user(&database, &token)
+        .await?
+        .ok_or_else(portal_only)?;
+    let mut stored = pending(&redis, &decision.user_code).await?;
+
+    stored.grant.status = if decision.approve {
+        DeviceStatus::Approved {
+            user_id: user.user_id,
+            auth_time: user.auth_time,
+            amr: user.amr,
+        }
+    } else {
+        DeviceStatus::Denied
+    };
+    if !stored.save_if_pending(&redis).await? {
+        return Err(invalid_user_code());
+    }
+
+    // Only recorded once the decision is saved, since the grant may have been
+    // decided or expired in the meantime.
+    if decision.approve {
+        consent::grant(
+            &database,
+            user.user_id,
+            stored.grant.client_id,
+            &stored.grant.scopes,
+        )
+        .await?;
+    }
+
+    tracing::info!(
+        user_id = %user.user_id,
+        client_id = %stored.grant.client_id,
+        approved = decision.approve,
+        "user decided on device grant"
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the pending grant of a user code.
+async fn pending(
+    redis: &bb8::Pool<RedisConnectionManager>,
+    user_code: &str,
+) -> OAuthResult<StoredGrant> {
+    DeviceGrant::find_by_user_code(redis, user_code)
+        .await?
+        .filter(|stored| stored.grant.status == DeviceStatus::Pending)
+        .ok_or_else(invalid_user_code)
+}
+
+/// Error returned when a user code doesn't exist, has expired or was already
+/// used.
+fn invalid_user_code() -> OAuthError {
+    OAuthError::invalid_request("The user code is invalid or expired.")
+}
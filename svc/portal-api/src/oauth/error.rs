@@ -0,0 +1,124 @@
+//! Error responses as described by [RFC 6749, section 5.2].
+//!
+//! OAuth clients expect errors in this format rather than the problem details
+//! returned by [`HandlerError`](lerpz_axum::error::HandlerError), so the
+//! OAuth endpoints use [`OAuthError`] instead.
+//!
+//! [RFC 6749, section 5.2]: https://www.rfc-editor.org/rfc/rfc6749#section-5.2
+
+use std::borrow::Cow;
+
+use axum::{
+    Json,
+    http::{StatusCode, header::CACHE_CONTROL},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// A type alias for [`Result<T, OAuthError>`].
+pub type OAuthResult<T> = std::result::Result<T, OAuthError>;
+
+/// The error codes defined by OAuth.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidRequest,
+    InvalidClient,
+    InvalidGrant,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    UnsupportedResponseType,
+    InvalidScope,
+    AccessDenied,
+    ServerError,
+    AuthorizationPending,
+    SlowDown,
+    ExpiredToken,
+}
+
+/// An error returned from an OAuth endpoint.
+#[derive(Serialize, Debug)]
+pub struct OAuthError {
+    error: ErrorCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_description: Option<Cow<'static, str>>,
+    /// The log ID of the error, if it was logged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_id: Option<String>,
+}
+
+impl OAuthError {
+    /// Create a new [`OAuthError`] with a description.
+    pub fn new(error: ErrorCode, description: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            error,
+            error_description: Some(description.into()),
+            log_id: None,
+        }
+    }
+
+    /// The request is missing a parameter or is otherwise malformed.
+    pub fn invalid_request(description: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCode::InvalidRequest, description)
+    }
+
+    /// The client is unknown or failed to authenticate.
+    pub fn invalid_client() -> Self {
+        Self::new(ErrorCode::InvalidClient, "Client authentication failed.")
+    }
+
+    /// The grant is invalid, expired, revoked or was issued to another client.
+    pub fn invalid_grant(description: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCode::InvalidGrant, description)
+    }
+
+    /// The error code of the error.
+    pub fn code(&self) -> ErrorCode {
+        self.error
+    }
+
+    /// The description of the error.
+    pub fn description(&self) -> Option<&str> {
+        self.error_description.as_deref()
+    }
+
+    /// The status code the error is returned with.
+    fn status(&self) -> StatusCode {
+        match self.error {
+            ErrorCode::InvalidClient => StatusCode::UNAUTHORIZED,
+            ErrorCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let mut response =
+            (self.status(), [(CACHE_CONTROL, "no-store")], Json(&self)).into_response();
+        if self.error == ErrorCode::InvalidClient {
+            response.headers_mut().insert(
+                "WWW-Authenticate",
+                "Basic realm=\"lerpz\"".parse().expect("valid header value"),
+            );
+        }
+        response
+    }
+}
+
+impl<E> From<E> for OAuthError
+where
+    E: Into<anyhow::Error>,
+{
+    /// Logs the error and hides it behind a `server_error`.
+    fn from(err: E) -> Self {
+        let log_id = Uuid::new_v4().to_string();
+        tracing::error!(log_id = %log_id, server_error = %err.into(), "An server error occurred");
+        Self {
+            error: ErrorCode::ServerError,
+            error_description: None,
+            log_id: Some(log_id),
+        }
+    }
+}
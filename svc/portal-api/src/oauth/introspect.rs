@@ -0,0 +1,176 @@
+use axum::{
+    Form, Json,
+    extract::{State, rejection::FormRejection},
+    http::{HeaderMap, header::CACHE_CONTROL},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use lerpz_axum::middleware::jwt::JwtConfig;
+use lerpz_jwt::Claims;
+use lerpz_pwd::TokenHasher;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    client,
+    error::{ErrorCode, OAuthError, OAuthResult},
+};
+use crate::tokens::refresh;
+
+/// The parameters of an introspection request.
+///
+/// The `token_type_hint` parameter is ignored, since the kind of token is
+/// told from the token itself.
+#[derive(Deserialize)]
+pub struct IntrospectRequest {
+    pub token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// What is known about a token.
+///
+/// Only `active` is set for tokens that are invalid, expired or revoked.
+#[derive(Serialize, Debug, Default)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+/// Tells a resource server whether a token is active, and what it grants.
+///
+/// Both access tokens and refresh tokens can be introspected. Only
+/// confidential clients may call this, since it reveals who a token belongs
+/// to.
+///
+/// See [RFC 7662](https://www.rfc-editor.org/rfc/rfc7662).
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(jwt): State<JwtConfig>,
+    State(hasher): State<TokenHasher>,
+    headers: HeaderMap,
+    form: Result<Form<IntrospectRequest>, FormRejection>,
+) -> OAuthResult<impl IntoResponse> {
+    let Form(request) = form.map_err(|err| OAuthError::invalid_request(err.body_text()))?;
+
+    let client = client::authenticate(
+        &database,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    if !client.confidential {
+        return Err(OAuthError::new(
+            ErrorCode::UnauthorizedClient,
+            "Only confidential clients can introspect tokens.",
+        ));
+    }
+
+    let token = request
+        .token
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("The token parameter is missing."))?;
+
+    let response = match refresh::find(&database, &hasher, token).await? {
+        Some(stored) => introspect_refresh(&database, stored).await?,
+        None => introspect_access(&database, &jwt, token).await?,
+    };
+
+    Ok(([(CACHE_CONTROL, "no-store")], Json(response)))
+}
+
+/// Introspects a refresh token.
+async fn introspect_refresh(
+    database: &PgPool,
+    stored: lerpz_model::RefreshToken,
+) -> OAuthResult<IntrospectResponse> {
+    let active =
+        stored.revoked_at.is_none() && stored.used_at.is_none() && stored.expires_at > Utc::now();
+    if !active {
+        return Ok(IntrospectResponse::default());
+    }
+
+    Ok(IntrospectResponse {
+        active,
+        scope: stored.scope,
+        client_id: Some(stored.client_id),
+        username: username(database, Some(stored.user_id)).await?,
+        token_type: Some("refresh_token"),
+        exp: Some(stored.expires_at.timestamp()),
+        iat: Some(stored.created_at.timestamp()),
+        sub: Some(stored.user_id.to_string()),
+        iss: Some(crate::config::issuer().to_string()),
+        ..Default::default()
+    })
+}
+
+/// Introspects an access token.
+///
+/// The token must be valid and its `jti` must be recorded in `access_tokens`
+/// without being revoked.
+async fn introspect_access(
+    database: &PgPool,
+    jwt: &JwtConfig,
+    token: &str,
+) -> OAuthResult<IntrospectResponse> {
+    let Ok(data) = jwt.keys().decode::<Claims>(token, jwt.verifier()) else {
+        return Ok(IntrospectResponse::default());
+    };
+    let claims = data.claims;
+
+    let recorded: Option<(Uuid, Option<Uuid>, Option<DateTime<Utc>>)> =
+        sqlx::query_as("SELECT client_id, user_id, revoked_at FROM access_tokens WHERE jti = $1")
+            .bind(&claims.jti)
+            .fetch_optional(database)
+            .await?;
+    let Some((client_id, user_id, None)) = recorded else {
+        return Ok(IntrospectResponse::default());
+    };
+
+    Ok(IntrospectResponse {
+        active: true,
+        scope: crate::tokens::join_scopes(&claims.scp),
+        client_id: Some(client_id),
+        username: username(database, user_id).await?,
+        token_type: Some("Bearer"),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        sub: Some(claims.sub),
+        aud: Some(claims.aud),
+        iss: Some(claims.iss),
+        jti: Some(claims.jti),
+    })
+}
+
+/// Returns the username of the user a token belongs to, if any.
+async fn username(database: &PgPool, user_id: Option<Uuid>) -> sqlx::Result<Option<String>> {
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+
+    sqlx::query_scalar("SELECT username FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(database)
+        .await
+}
@@ -0,0 +1,61 @@
+//! OAuth 2.0 endpoints for third party clients.
+//!
+//! The authorization code grant with PKCE, the refresh token grant, the
+//! client credentials grant and the device authorization grant are supported,
+//! see [RFC 6749], [RFC 7636] and [RFC 8628].
+//! Tokens can be introspected and revoked as described by [RFC 7662] and
+//! [RFC 7009]. Clients granted the `openid` scope get an OpenID Connect ID
+//! token and can read claims about the user from `/userinfo`.
+//!
+//! [RFC 6749]: https://www.rfc-editor.org/rfc/rfc6749
+//! [RFC 7636]: https://www.rfc-editor.org/rfc/rfc7636
+//! [RFC 8628]: https://www.rfc-editor.org/rfc/rfc8628
+//! [RFC 7662]: https://www.rfc-editor.org/rfc/rfc7662
+//! [RFC 7009]: https://www.rfc-editor.org/rfc/rfc7009
+
+use crate::state::AppState;
+
+use axum::{
+    Router,
+    handler::Handler,
+    routing::{get, post},
+};
+use lerpz_axum::middleware::{
+    guard::RequireScope,
+    jwt::{AccessToken, Unrevoked},
+};
+
+mod authorize;
+mod client;
+mod code;
+pub mod consent;
+mod device;
+mod device_authorization;
+mod device_verification;
+mod error;
+mod introspect;
+mod pkce;
+mod revoke;
+pub mod secret;
+mod token;
+mod userinfo;
+
+pub fn router(state: AppState) -> Router<AppState> {
+    let openid = RequireScope::<Unrevoked<AccessToken>, _>::new(state.clone(), "openid");
+
+    Router::new()
+        .route("/authorize", post(authorize::handler))
+        .route("/token", post(token::handler))
+        .route("/device_authorization", post(device_authorization::handler))
+        .route(
+            "/device",
+            get(device_verification::details).post(device_verification::decide),
+        )
+        .route("/introspect", post(introspect::handler))
+        .route("/revoke", post(revoke::handler))
+        .route(
+            "/userinfo",
+            get(userinfo::handler.layer(openid.clone())).post(userinfo::handler.layer(openid)),
+        )
+        .with_state(state)
+}
@@ -0,0 +1,97 @@
+use axum::{
+    Form,
+    extract::{State, rejection::FormRejection},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::DateTime;
+use lerpz_axum::middleware::jwt::{JwtConfig, RevocationList};
+use lerpz_jwt::Claims;
+use lerpz_pwd::TokenHasher;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    client,
+    error::{OAuthError, OAuthResult},
+};
+use crate::tokens::refresh;
+
+/// The parameters of a revocation request.
+///
+/// The `token_type_hint` parameter is ignored, since the kind of token is
+/// told from the token itself.
+#[derive(Deserialize)]
+pub struct RevokeRequest {
+    pub token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Revokes an access token or a refresh token.
+///
+/// Revoking a refresh token revokes every refresh token rotated from the same
+/// grant, along with the access tokens issued from them.
+///
+/// Tokens that are invalid, already revoked or were issued to another client
+/// are ignored, and the response is the same as for a revoked token, so this
+/// can't be used to find out whether a token exists.
+///
+/// See [RFC 7009](https://www.rfc-editor.org/rfc/rfc7009).
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(revocations): State<RevocationList>,
+    State(jwt): State<JwtConfig>,
+    State(hasher): State<TokenHasher>,
+    headers: HeaderMap,
+    form: Result<Form<RevokeRequest>, FormRejection>,
+) -> OAuthResult<StatusCode> {
+    let Form(request) = form.map_err(|err| OAuthError::invalid_request(err.body_text()))?;
+
+    let client = client::authenticate(
+        &database,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?
+    .client;
+
+    let token = request
+        .token
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("The token parameter is missing."))?;
+
+    if let Some(stored) = refresh::find(&database, &hasher, token).await? {
+        if stored.client_id == client.id {
+            let (revoked, revoked_access) =
+                refresh::revoke_grant(&database, &revocations, stored.family_id).await?;
+            tracing::info!(
+                client_id = %client.id,
+                family_id = %stored.family_id,
+                revoked,
+                revoked_access,
+                "revoked refresh token family"
+            );
+        }
+        return Ok(StatusCode::OK);
+    }
+
+    // Expired access tokens can't be used anyway, so they are ignored too.
+    let Ok(data) = jwt.keys().decode::<Claims>(token, jwt.verifier()) else {
+        return Ok(StatusCode::OK);
+    };
+    let issued_to: Option<Uuid> =
+        sqlx::query_scalar("SELECT client_id FROM access_tokens WHERE jti = $1")
+            .bind(&data.claims.jti)
+            .fetch_optional(&database)
+            .await?;
+    if issued_to == Some(client.id)
+        && let Some(expires_at) = DateTime::from_timestamp(data.claims.exp, 0)
+    {
+        revocations.revoke(&data.claims.jti, expires_at).await?;
+        tracing::info!(client_id = %client.id, jti = %data.claims.jti, "revoked access token");
+    }
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,161 @@
+//! Secrets of confidential clients.
+//!
+//! Secrets are generated with [`generate_token`], but stored like passwords
+//! using [`hash_pwd`], since they are long-lived and a leaked database
+//! shouldn't be enough to use them.
+//!
+//! A client has at most two valid secrets. Rotating the secret keeps the
+//! previous one valid for [`ROTATION_GRACE`], so that the client can be
+//! updated to the new secret without downtime.
+//!
+//! A client without any secret can be given its first one with [`bootstrap`],
+//! which is how the admin client gets the secret needed to call the client
+//! endpoints.
+
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+use lerpz_model::ClientSecret;
+use lerpz_pwd::{
+    SecretString, Verdict, check_token, generate_token, hash_pwd, token_prefix, verify_pwd,
+};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// The prefix of client secrets.
+pub const PREFIX: &str = "lrpz_cs";
+
+/// How long the previous secret stays valid after the secret was rotated.
+pub const ROTATION_GRACE: Duration = Duration::days(7);
+
+/// Issues a new secret for the client.
+///
+/// The newest of the current secrets stays valid until [`ROTATION_GRACE`] has
+/// passed, and any older secret is revoked. Returns the new secret along with
+/// when the previous secret expires, if there is one.
+pub async fn rotate(
+    tx: &mut Transaction<'_, Postgres>,
+    client_id: Uuid,
+) -> anyhow::Result<(SecretString, Option<DateTime<Utc>>)> {
+    let secret = generate_token(PREFIX)?;
+    let secret_hash = hash_pwd(&secret).await?;
+
+    // Serializes rotations of the same client.
+    sqlx::query("SELECT id FROM oauth_clients WHERE id = $1 FOR UPDATE")
+        .bind(client_id)
+        .execute(&mut **tx)
+        .await?;
+
+    let current: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM client_secrets \
+         WHERE client_id = $1 AND revoked_at IS NULL \
+         AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP) \
+         ORDER BY created_at DESC LIMIT 1",
+    )
+    .bind(client_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE client_secrets SET revoked_at = CURRENT_TIMESTAMP \
+         WHERE client_id = $1 AND revoked_at IS NULL AND id IS DISTINCT FROM $2",
+    )
+    .bind(client_id)
+    .bind(current.map(|(id,)| id))
+    .execute(&mut **tx)
+    .await?;
+
+    let previous_expires_at = match current {
+        Some((id,)) => {
+            let (expires_at,): (DateTime<Utc>,) = sqlx::query_as(
+                "UPDATE client_secrets SET expires_at = LEAST(COALESCE(expires_at, $2), $2) \
+                 WHERE id = $1 RETURNING expires_at",
+            )
+            .bind(id)
+            .bind(Utc::now() + ROTATION_GRACE)
+            .fetch_one(&mut **tx)
+            .await?;
+            Some(expires_at)
+        }
+        None => None,
+    };
+
+    sqlx::query("INSERT INTO client_secrets(client_id, secret_hash) VALUES ($1, $2)")
+        .bind(client_id)
+        .bind(secret_hash)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok((secret, previous_expires_at))
+}
+
+/// Issues the first secret of a client and writes it to a file.
+///
+/// Does nothing if the client has ever been issued a secret, so it's safe to
+/// call on every startup. The secret is only stored if it could be written,
+/// so it can't get lost. Returns whether a secret was issued.
+pub async fn bootstrap(database: &PgPool, client_id: Uuid, path: &Path) -> anyhow::Result<bool> {
+    let mut tx = database.begin().await?;
+
+    sqlx::query("SELECT id FROM oauth_clients WHERE id = $1 FOR UPDATE")
+        .bind(client_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if is_confidential(&mut *tx, client_id).await? {
+        return Ok(false);
+    }
+
+    let (secret, _) = rotate(&mut tx, client_id).await?;
+    std::fs::write(path, secret.expose_secret())?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Whether the client has ever been issued a secret.
+///
+/// Such a client is confidential and must always authenticate, even once all
+/// of its secrets have expired.
+pub async fn is_confidential(executor: impl PgExecutor<'_>, client_id: Uuid) -> sqlx::Result<bool> {
+    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM client_secrets WHERE client_id = $1)")
+        .bind(client_id)
+        .fetch_one(executor)
+        .await
+}
+
+/// Whether the secret is one of the valid secrets of the client.
+///
+/// If the matching hash was made with an outdated scheme, it is replaced.
+pub async fn verify(database: &PgPool, client_id: Uuid, secret: &str) -> anyhow::Result<bool> {
+    if !check_token(secret) || token_prefix(secret) != Some(PREFIX) {
+        return Ok(false);
+    }
+
+    let secrets: Vec<ClientSecret> = sqlx::query_as(
+        "SELECT * FROM client_secrets \
+         WHERE client_id = $1 AND revoked_at IS NULL \
+         AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
+    )
+    .bind(client_id)
+    .fetch_all(database)
+    .await?;
+
+    for stored in secrets {
+        match verify_pwd(&stored.secret_hash, secret).await? {
+            Verdict::Invalid => continue,
+            Verdict::Valid { new_hash, .. } => {
+                if let Some(new_hash) = new_hash {
+                    sqlx::query("UPDATE client_secrets SET secret_hash = $1 WHERE id = $2")
+                        .bind(new_hash)
+                        .bind(stored.id)
+                        .execute(database)
+                        .await?;
+                }
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
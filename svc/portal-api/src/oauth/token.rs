@@ -0,0 +1,411 @@
+use axum::{
+    Form, Json,
+    extract::{State, rejection::FormRejection},
+    http::{
+        HeaderMap,
+        header::{CACHE_CONTROL, PRAGMA},
+    },
+    response::IntoResponse,
+};
+use bb8_redis::RedisConnectionManager;
+use chrono::Utc;
+use lerpz_axum::middleware::{guard::ScopeHierarchy, jwt::RevocationList};
+use lerpz_jwt::KeyRing;
+use lerpz_model::{OAuthClient, User};
+use lerpz_pwd::TokenHasher;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    client::{self, AuthenticatedClient},
+    code::{self, AuthorizationCode},
+    device::{self, DeviceGrant, DeviceStatus},
+    error::{ErrorCode, OAuthError, OAuthResult},
+    pkce,
+};
+use crate::tokens::{
+    TokenResponse, access, client_scopes,
+    id::{IdToken, OPENID_SCOPE},
+    issue_pair, join_scopes,
+    refresh::{self, RefreshError},
+    split_scopes,
+};
+
+/// The parameters of a token request.
+///
+/// Which parameters are used depends on the `grant_type`.
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub device_code: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// The grant type of the device authorization grant.
+pub const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Exchanges a grant for an access and refresh token.
+///
+/// Supports the `authorization_code` grant, which requires PKCE, the
+/// `refresh_token` grant, the device authorization grant and, for
+/// confidential clients, the `client_credentials` grant.
+///
+/// See [RFC 6749, section 3.2](https://www.rfc-editor.org/rfc/rfc6749#section-3.2).
+#[allow(clippy::too_many_arguments)]
+pub async fn handler(
+    State(database): State<PgPool>,
+    State(redis): State<bb8::Pool<RedisConnectionManager>>,
+    State(revocations): State<RevocationList>,
+    State(keys): State<KeyRing>,
+    State(hasher): State<TokenHasher>,
+    State(hierarchy): State<ScopeHierarchy>,
+    headers: HeaderMap,
+    form: Result<Form<TokenRequest>, FormRejection>,
+) -> OAuthResult<impl IntoResponse> {
+    let Form(request) = form.map_err(|err| OAuthError::invalid_request(err.body_text()))?;
+
+    let client = client::authenticate(
+        &database,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+
+    let response = match request.grant_type.as_deref() {
+        Some("authorization_code") => {
+            authorization_code(&database, &redis, &keys, &hasher, &client.client, &request).await?
+        }
+        Some("refresh_token") => {
+            refresh_token(
+                &database,
+                &revocations,
+                &keys,
+                &hasher,
+                &hierarchy,
+                &client.client,
+                &request,
+            )
+            .await?
+        }
+        Some("client_credentials") => {
+            client_credentials(&database, &keys, &hierarchy, &client, &request).await?
+        }
+        Some(DEVICE_CODE_GRANT) => {
+            device_code(&database, &redis, &keys, &hasher, &client.client, &request).await?
+        }
+        Some(_) => {
+            return Err(OAuthError::new(
+                ErrorCode::UnsupportedGrantType,
+                "The grant type is not supported.",
+            ));
+        }
+        None => {
+            return Err(OAuthError::invalid_request(
+                "The grant_type parameter is missing.",
+            ));
+        }
+    };
+
+    Ok((
+        [(CACHE_CONTROL, "no-store"), (PRAGMA, "no-cache")],
+        Json(response),
+    ))
+}
+
+/// Redeems an authorization code.
+///
+/// If the `openid` scope was granted, an OpenID Connect ID token is issued
+/// along with the access token.
+///
+/// See [RFC 6749, section 4.1.3](https://www.rfc-editor.org/rfc/rfc6749#section-4.1.3).
+async fn authorization_code(
+    database: &PgPool,
+    redis: &bb8::Pool<RedisConnectionManager>,
+    keys: &KeyRing,
+    hasher: &TokenHasher,
+    client: &OAuthClient,
+    request: &TokenRequest,
+) -> OAuthResult<TokenResponse> {
+    let code = request
+        .code
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("The code parameter is missing."))?;
+    let verifier = request
+        .code_verifier
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("The code_verifier parameter is missing."))?;
+
+    // The code is taken before it is checked, so a code sent by the wrong
+    // client or with the wrong verifier can't be tried again.
+    let invalid = || OAuthError::invalid_grant("The authorization code is invalid or expired.");
+    if lerpz_pwd::token_prefix(code) != Some(code::PREFIX) {
+        return Err(invalid());
+    }
+    let grant = AuthorizationCode::take(redis, hasher, code)
+        .await?
+        .ok_or_else(invalid)?;
+
+    if grant.client_id != client.id {
+        return Err(invalid());
+    }
+    if grant.redirect_uri_given && request.redirect_uri.as_deref() != Some(&grant.redirect_uri) {
+        return Err(OAuthError::invalid_grant(
+            "The redirect_uri doesn't match the authorization request.",
+        ));
+    }
+    if !pkce::verify(verifier, &grant.code_challenge) {
+        return Err(OAuthError::invalid_grant("The code_verifier is invalid."));
+    }
+
+    let mut tx = database.begin().await?;
+    let user = find_user(&mut tx, grant.user_id).await?;
+    let mut response =
+        issue_pair(&mut tx, keys, hasher, &user, client.id, &grant.scopes, None).await?;
+
+    if grant.scopes.contains(OPENID_SCOPE) {
+        let id_token = IdToken {
+            user_id: user.id,
+            client_id: client.id,
+            auth_time: grant.auth_time,
+            nonce: grant.nonce.as_deref(),
+            amr: &grant.amr,
+            access_token: &response.access_token,
+        };
+        response.id_token = Some(id_token.issue(keys)?);
+    }
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+/// Redeems a refresh token.
+///
+/// The scopes of the new access token can be narrowed with `scope`, but the
+/// new refresh token keeps the original scopes.
+///
+/// See [RFC 6749, section 6](https://www.rfc-editor.org/rfc/rfc6749#section-6).
+async fn refresh_token(
+    database: &PgPool,
+    revocations: &RevocationList,
+    keys: &KeyRing,
+    hasher: &TokenHasher,
+    hierarchy: &ScopeHierarchy,
+    client: &OAuthClient,
+    request: &TokenRequest,
+) -> OAuthResult<TokenResponse> {
+    let token = request
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("The refresh_token parameter is missing."))?;
+
+    // Returning early drops the transaction, which rolls back marking the
+    // token as used.
+    let (mut tx, parent) = refresh::redeem(database, revocations, hasher, token)
+        .await
+        .map_err(|err| match err {
+            RefreshError::Invalid | RefreshError::Reused(_) => OAuthError::invalid_grant(
+                "The refresh token is invalid, expired or was already used.",
+            ),
+            err => err.into(),
+        })?;
+    if parent.client_id != client.id {
+        return Err(OAuthError::invalid_grant(
+            "The refresh token was issued to another client.",
+        ));
+    }
+
+    let granted = split_scopes(parent.scope.as_deref());
+    let scopes = match request.scope.as_deref() {
+        Some(scope) => {
+            let requested = split_scopes(Some(scope));
+            if !requested
+                .iter()
+                .all(|scope| client::grants(&granted, scope, hierarchy))
+            {
+                return Err(OAuthError::new(
+                    ErrorCode::InvalidScope,
+                    "The requested scopes exceed the ones originally granted.",
+                ));
+            }
+            requested
+        }
+        None => granted,
+    };
+
+    let user = find_user(&mut tx, parent.user_id).await?;
+    let response = issue_pair(
+        &mut tx,
+        keys,
+        hasher,
+        &user,
+        client.id,
+        &scopes,
+        Some(&parent),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+/// Issues an access token to the client itself.
+///
+/// Only confidential clients can use this grant, and no refresh token is
+/// issued, since the client can simply request a new access token.
+///
+/// See [RFC 6749, section 4.4](https://www.rfc-editor.org/rfc/rfc6749#section-4.4).
+async fn client_credentials(
+    database: &PgPool,
+    keys: &KeyRing,
+    hierarchy: &ScopeHierarchy,
+    AuthenticatedClient {
+        client,
+        confidential,
+    }: &AuthenticatedClient,
+    request: &TokenRequest,
+) -> OAuthResult<TokenResponse> {
+    if !confidential {
+        return Err(OAuthError::new(
+            ErrorCode::UnauthorizedClient,
+            "Only confidential clients can use the client_credentials grant.",
+        ));
+    }
+
+    let mut tx = database.begin().await?;
+    let allowed = client_scopes(&mut *tx, client.id).await?;
+    let scopes = client::select_scopes(request.scope.as_deref(), &allowed, hierarchy)?;
+    let access_token = access::issue_client(&mut tx, keys, client.id, &scopes).await?;
+    tx.commit().await?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: access::LIFETIME.num_seconds(),
+        refresh_token: None,
+        scope: join_scopes(&scopes),
+        id_token: None,
+    })
+}
+
+/// Polls for the outcome of a device authorization grant.
+///
+/// Until the user has approved or denied the device, this fails with
+/// `authorization_pending`, or `slow_down` if the device polls more often
+/// than the interval allows, in which case the interval grows.
+///
+/// See [RFC 8628, section 3.4](https://www.rfc-editor.org/rfc/rfc8628#section-3.4).
+async fn device_code(
+    database: &PgPool,
+    redis: &bb8::Pool<RedisConnectionManager>,
+    keys: &KeyRing,
+    hasher: &TokenHasher,
+    client: &OAuthClient,
+    request: &TokenRequest,
+) -> OAuthResult<TokenResponse> {
+    let device_code = request
+        .device_code
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("The device_code parameter is missing."))?;
+
+    let invalid = || OAuthError::invalid_grant("The device code is invalid.");
+    if lerpz_pwd::token_prefix(device_code) != Some(device::PREFIX) {
+        return Err(invalid());
+    }
+    let mut stored = DeviceGrant::find(redis, hasher, device_code)
+        .await?
+        .ok_or_else(|| OAuthError::new(ErrorCode::ExpiredToken, "The device code has expired."))?;
+    if stored.grant.client_id != client.id {
+        return Err(invalid());
+    }
+
+    let (user_id, auth_time, amr) = match stored.grant.status.clone() {
+        DeviceStatus::Pending => {
+            let now = Utc::now().timestamp();
+            let too_soon = stored
+                .grant
+                .last_polled_at
+                .is_some_and(|at| now - at < stored.grant.interval as i64);
+            stored.grant.last_polled_at = Some(now);
+            if too_soon {
+                stored.grant.interval += device::SLOW_DOWN;
+            }
+            // The user decided while this poll was running, which the next
+            // poll will pick up.
+            if !stored.save_if_pending(redis).await? {
+                return Err(OAuthError::new(
+                    ErrorCode::AuthorizationPending,
+                    "The user hasn't approved the device yet.",
+                ));
+            }
+
+            return Err(if too_soon {
+                OAuthError::new(
+                    ErrorCode::SlowDown,
+                    format!("Wait {} seconds between polls.", stored.grant.interval),
+                )
+            } else {
+                OAuthError::new(
+                    ErrorCode::AuthorizationPending,
+                    "The user hasn't approved the device yet.",
+                )
+            });
+        }
+        DeviceStatus::Denied => {
+            stored.remove(redis).await?;
+            return Err(OAuthError::new(
+                ErrorCode::AccessDenied,
+                "The user denied the device.",
+            ));
+        }
+        DeviceStatus::Approved {
+            user_id,
+            auth_time,
+            amr,
+        } => (user_id, auth_time, amr),
+    };
+
+    // Only the poll that removes the grant gets the tokens.
+    if !stored.remove(redis).await? {
+        return Err(invalid());
+    }
+
+    let mut tx = database.begin().await?;
+    let user = find_user(&mut tx, user_id).await?;
+    let scopes = &stored.grant.scopes;
+    let mut response = issue_pair(&mut tx, keys, hasher, &user, client.id, scopes, None).await?;
+
+    if scopes.contains(OPENID_SCOPE) {
+        let id_token = IdToken {
+            user_id: user.id,
+            client_id: client.id,
+            auth_time,
+            nonce: None,
+            amr: &amr,
+            access_token: &response.access_token,
+        };
+        response.id_token = Some(id_token.issue(keys)?);
+    }
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+/// Returns the user a grant was issued to.
+async fn find_user(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: Uuid,
+) -> OAuthResult<User> {
+    sqlx::query_as("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| OAuthError::invalid_grant("The user of the grant no longer exists."))
+}
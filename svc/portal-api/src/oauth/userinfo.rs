@@ -0,0 +1,95 @@
+use axum::{Json, extract::State};
+use lerpz_axum::{
+    error::{HandlerError, HandlerResult},
+    middleware::jwt::{AccessToken, Unrevoked},
+};
+use lerpz_model::User;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The claims about a user that a client is allowed to read.
+///
+/// See [OpenID Connect Core 1.0, section 5.1](https://openid.net/specs/openid-connect-core-1_0.html#StandardClaims).
+#[derive(Serialize, Debug)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+impl UserInfo {
+    /// Picks the claims of the user that the scopes allow.
+    ///
+    /// - `profile`: `preferred_username`, `picture` and `updated_at`.
+    /// - `email`: `email`.
+    pub fn new(user: User, has_scope: impl Fn(&str) -> bool) -> Self {
+        let profile = has_scope("profile");
+        Self {
+            sub: user.id.to_string(),
+            preferred_username: profile.then_some(user.username),
+            picture: user.avatar.filter(|_| profile),
+            updated_at: profile.then(|| user.updated_at.timestamp()),
+            email: has_scope("email").then_some(user.primary_email),
+        }
+    }
+}
+
+/// Returns the claims about the user that the token grants access to.
+///
+/// The token must have the `openid` scope, which is checked by the router.
+pub async fn handler(
+    State(database): State<PgPool>,
+    Unrevoked(token): Unrevoked<AccessToken>,
+) -> HandlerResult<Json<UserInfo>> {
+    let user_id = token
+        .claims
+        .sub
+        .parse::<Uuid>()
+        .map_err(|_| HandlerError::unauthorized())?;
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&database)
+        .await?
+        .ok_or_else(HandlerError::unauthorized)?;
+
+    Ok(Json(UserInfo::new(user, |scope| token.has_scope(scope))))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn test_user_info_scopes() {
+        let user = User {
+            id: Uuid::nil(),
+            username: "kasper".into(),
+            primary_email: "kasper@lerpz.local".into(),
+            password_hash: String::new(),
+            password_salt: None,
+            password_breached_at: None,
+            avatar: Some("https://lerpz.local/avatar.png".into()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            organization_id: None,
+        };
+
+        let info = UserInfo::new(user.clone(), |_| false);
+        assert_eq!(info.sub, Uuid::nil().to_string());
+        assert!(info.preferred_username.is_none() && info.picture.is_none());
+        assert!(info.email.is_none());
+
+        let info = UserInfo::new(user, |scope| scope == "email");
+        assert_eq!(info.email.as_deref(), Some("kasper@lerpz.local"));
+        assert!(info.preferred_username.is_none());
+    }
+}
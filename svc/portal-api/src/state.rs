@@ -1,10 +1,21 @@
 use axum::extract::FromRef;
+use lerpz_axum::middleware::{
+    guard::ScopeHierarchy,
+    jwt::{JwtConfig, RevocationList},
+};
+use lerpz_jwt::KeyRing;
+use lerpz_pwd::{BreachChecker, TokenHasher};
 use sqlx::{Pool, Postgres};
 
 #[derive(Clone)]
 pub struct AppState {
     pub database: sqlx::PgPool,
     pub redis: bb8::Pool<bb8_redis::RedisConnectionManager>,
+    pub breaches: Option<BreachChecker>,
+    pub jwt: JwtConfig,
+    pub revocations: RevocationList,
+    pub token_hasher: TokenHasher,
+    pub scopes: ScopeHierarchy,
 }
 
 impl FromRef<AppState> for Pool<Postgres> {
@@ -18,3 +29,39 @@ impl FromRef<AppState> for bb8::Pool<bb8_redis::RedisConnectionManager> {
         state.redis.clone()
     }
 }
+
+impl FromRef<AppState> for Option<BreachChecker> {
+    fn from_ref(state: &AppState) -> Self {
+        state.breaches.clone()
+    }
+}
+
+impl FromRef<AppState> for JwtConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.jwt.clone()
+    }
+}
+
+impl FromRef<AppState> for KeyRing {
+    fn from_ref(state: &AppState) -> Self {
+        state.jwt.keys().clone()
+    }
+}
+
+impl FromRef<AppState> for RevocationList {
+    fn from_ref(state: &AppState) -> Self {
+        state.revocations.clone()
+    }
+}
+
+impl FromRef<AppState> for TokenHasher {
+    fn from_ref(state: &AppState) -> Self {
+        state.token_hasher.clone()
+    }
+}
+
+impl FromRef<AppState> for ScopeHierarchy {
+    fn from_ref(state: &AppState) -> Self {
+        state.scopes.clone()
+    }
+}
@@ -0,0 +1,150 @@
+//! Access tokens signed with the active key of the key ring.
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Duration, Utc};
+use lerpz_jwt::{Claims, KeyRing, UserClaims, claims::DEFAULT_LIFETIME};
+use lerpz_model::User;
+use sqlx::{PgExecutor, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::PORTAL_CLIENT_ID;
+use crate::config;
+
+/// How long an access token is valid.
+///
+/// This is kept short, since an access token can be used without asking the
+/// database until it expires, unless its revocation is looked up.
+pub const LIFETIME: Duration = DEFAULT_LIFETIME;
+
+/// How users authenticate, see [`UserClaims::amr`].
+///
+/// Users can only log in with a password for now.
+pub const AMR: [&str; 1] = ["pwd"];
+
+/// A user signed in to the portal.
+pub struct PortalUser {
+    pub user_id: Uuid,
+    /// When the user logged in, as a Unix timestamp.
+    pub auth_time: i64,
+    /// How the user logged in.
+    pub amr: Vec<String>,
+}
+
+/// Signs an access token for the user.
+///
+/// The token is recorded in `access_tokens`, so that it can be revoked by its
+/// `jti` before it expires, or together with the refresh token family it was
+/// issued with.
+pub async fn issue(
+    tx: &mut Transaction<'_, Postgres>,
+    keys: &KeyRing,
+    user: &User,
+    client_id: Uuid,
+    family_id: Uuid,
+    scopes: &BTreeSet<String>,
+) -> anyhow::Result<String> {
+    let issuer = config::issuer();
+    let mut claims = UserClaims::from(user.clone()).with_claims(|claims| {
+        claims
+            .with_issuer(issuer)
+            .with_audience(issuer)
+            .with_scopes(scopes.iter().cloned())
+            .with_lifetime(LIFETIME)
+    });
+    claims.amr = AMR.map(String::from).to_vec();
+    let token = keys.encode(&claims)?;
+    record(
+        tx,
+        &claims.claims,
+        Some(user.id),
+        client_id,
+        Some(family_id),
+        scopes,
+    )
+    .await?;
+
+    Ok(token)
+}
+
+/// Signs an access token for the client itself, for the `client_credentials`
+/// grant.
+///
+/// The subject of the token is the id of the client, and it is recorded
+/// without a user.
+pub async fn issue_client(
+    tx: &mut Transaction<'_, Postgres>,
+    keys: &KeyRing,
+    client_id: Uuid,
+    scopes: &BTreeSet<String>,
+) -> anyhow::Result<String> {
+    let issuer = config::issuer();
+    let claims = Claims::default()
+        .with_issuer(issuer)
+        .with_audience(issuer)
+        .with_subject(client_id.to_string())
+        .with_scopes(scopes.iter().cloned())
+        .with_lifetime(LIFETIME);
+    let token = keys.encode(&claims)?;
+    record(tx, &claims, None, client_id, None, scopes).await?;
+
+    Ok(token)
+}
+
+/// Records an issued token in `access_tokens`.
+async fn record(
+    tx: &mut Transaction<'_, Postgres>,
+    claims: &Claims,
+    user_id: Option<Uuid>,
+    client_id: Uuid,
+    family_id: Option<Uuid>,
+    scopes: &BTreeSet<String>,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO access_tokens(jti, user_id, client_id, family_id, scope, expires_at) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(&claims.jti)
+    .bind(user_id)
+    .bind(client_id)
+    .bind(family_id)
+    .bind(super::join_scopes(scopes))
+    .bind(DateTime::from_timestamp(claims.exp, 0))
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the user of an access token, if it was issued to the portal.
+///
+/// Only the portal may act on behalf of the user, like authorizing other
+/// clients or managing consents, so [`None`] is returned for tokens issued to
+/// any other client.
+pub async fn portal_user(
+    executor: impl PgExecutor<'_>,
+    token: &UserClaims,
+) -> sqlx::Result<Option<PortalUser>> {
+    // The user logged in when the first refresh token of the family was
+    // issued, since refreshing doesn't ask for the password again.
+    let issued_to: Option<(Uuid, Option<DateTime<Utc>>)> = sqlx::query_as(
+        "SELECT a.client_id, \
+         (SELECT MIN(r.created_at) FROM refresh_tokens r WHERE r.family_id = a.family_id) \
+         FROM access_tokens a WHERE a.jti = $1",
+    )
+    .bind(&token.claims.jti)
+    .fetch_optional(executor)
+    .await?;
+    let Some((PORTAL_CLIENT_ID, logged_in_at)) = issued_to else {
+        return Ok(None);
+    };
+    let Ok(user_id) = token.claims.sub.parse() else {
+        return Ok(None);
+    };
+
+    Ok(Some(PortalUser {
+        user_id,
+        auth_time: logged_in_at.map_or(token.claims.iat, |at| at.timestamp()),
+        amr: token.amr.clone(),
+    }))
+}
@@ -0,0 +1,53 @@
+//! OpenID Connect ID tokens.
+
+use chrono::Duration;
+use lerpz_jwt::{Claims, IdTokenClaims, KeyRing};
+use uuid::Uuid;
+
+use crate::config;
+
+/// The scope a client must be granted to get an ID token.
+pub const OPENID_SCOPE: &str = "openid";
+
+/// How long an ID token is valid.
+///
+/// An ID token is only used to sign the user in to the client, so it doesn't
+/// need to outlive the access token by much.
+pub const LIFETIME: Duration = Duration::hours(1);
+
+/// What an ID token is issued for.
+pub struct IdToken<'a> {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    /// When the user authenticated, as a Unix timestamp.
+    pub auth_time: i64,
+    pub nonce: Option<&'a str>,
+    pub amr: &'a [String],
+    /// The access token issued together with the ID token.
+    pub access_token: &'a str,
+}
+
+impl IdToken<'_> {
+    /// Signs the ID token with the active key.
+    ///
+    /// The audience of the token is the client, not this issuer.
+    pub fn issue(&self, keys: &KeyRing) -> anyhow::Result<String> {
+        let key = keys
+            .active()
+            .ok_or_else(|| anyhow::anyhow!("no active key to sign ID tokens with"))?;
+
+        let claims = Claims::default()
+            .with_issuer(config::issuer())
+            .with_audience(self.client_id.to_string())
+            .with_subject(self.user_id.to_string())
+            .with_lifetime(LIFETIME);
+        let mut claims = IdTokenClaims::new(claims, self.auth_time)
+            .with_amr(self.amr)
+            .with_access_token(self.access_token, key.algorithm());
+        if let Some(nonce) = self.nonce {
+            claims = claims.with_nonce(nonce);
+        }
+
+        Ok(keys.encode(&claims)?)
+    }
+}
@@ -0,0 +1,137 @@
+//! Issuing access and refresh tokens.
+
+use std::collections::BTreeSet;
+
+use lerpz_jwt::KeyRing;
+use lerpz_model::{RefreshToken, User};
+use lerpz_pwd::TokenHasher;
+use serde::Serialize;
+use sqlx::{PgExecutor, Postgres, Transaction};
+use uuid::{Uuid, uuid};
+
+pub mod access;
+pub mod id;
+pub mod refresh;
+
+/// The id of the "Lerpz Portal" client seeded by the initial migration.
+///
+/// Tokens issued by logging in directly are issued to this client.
+pub const PORTAL_CLIENT_ID: Uuid = uuid!("cdd37e5a-a554-4535-bff2-45ba130b05b4");
+
+/// The id of the "Lerpz Admin" client seeded by the `admin_client` migration.
+///
+/// It is granted the `clients` scope, so it can manage the other clients.
+pub const ADMIN_CLIENT_ID: Uuid = uuid!("5f0b8c1e-7d2a-4c61-9e3b-2a8d4f6c1b70");
+
+/// Response containing a new access and refresh token.
+///
+/// See [RFC 6749, section 5.1](https://www.rfc-editor.org/rfc/rfc6749#section-5.1).
+#[derive(Serialize, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+/// Issues an access token and a refresh token for the user.
+///
+/// The refresh token starts a new family, unless it replaces `parent`. It
+/// keeps the scopes of `parent`, so narrowing the scopes of one access token
+/// doesn't narrow the ones issued after it.
+pub async fn issue_pair(
+    tx: &mut Transaction<'_, Postgres>,
+    keys: &KeyRing,
+    hasher: &TokenHasher,
+    user: &User,
+    client_id: Uuid,
+    scopes: &BTreeSet<String>,
+    parent: Option<&RefreshToken>,
+) -> anyhow::Result<TokenResponse> {
+    let scope = join_scopes(scopes);
+    let refresh_scope = match parent {
+        Some(parent) => parent.scope.clone(),
+        None => scope.clone(),
+    };
+    let family_id = parent.map_or_else(Uuid::new_v4, |parent| parent.family_id);
+    let access_token = access::issue(tx, keys, user, client_id, family_id, scopes).await?;
+    let refresh_token = refresh::issue(
+        tx,
+        hasher,
+        user.id,
+        client_id,
+        family_id,
+        refresh_scope.as_deref(),
+        parent,
+    )
+    .await?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: access::LIFETIME.num_seconds(),
+        refresh_token: Some(refresh_token.expose_secret().to_string()),
+        scope,
+        id_token: None,
+    })
+}
+
+/// Returns the names of the scopes the client is allowed to request.
+pub async fn client_scopes(
+    executor: impl PgExecutor<'_>,
+    client_id: Uuid,
+) -> sqlx::Result<BTreeSet<String>> {
+    let scopes: Vec<(String,)> = sqlx::query_as(
+        "SELECT s.name FROM scopes s \
+         JOIN client_scopes cs ON cs.scope_id = s.id \
+         WHERE cs.client_id = $1",
+    )
+    .bind(client_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(scopes.into_iter().map(|(name,)| name).collect())
+}
+
+/// Splits a space separated list of scopes.
+pub fn split_scopes(scope: Option<&str>) -> BTreeSet<String> {
+    scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+/// Joins scopes into a space separated list, or [`None`] if there are none.
+pub fn join_scopes(scopes: &BTreeSet<String>) -> Option<String> {
+    (!scopes.is_empty()).then(|| scopes.iter().cloned().collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_scopes() {
+        assert!(split_scopes(None).is_empty());
+        assert!(split_scopes(Some("  ")).is_empty());
+        assert_eq!(
+            split_scopes(Some("profile  openid profile\temail")),
+            BTreeSet::from(["email".into(), "openid".into(), "profile".into()])
+        );
+    }
+
+    #[test]
+    fn test_join_scopes() {
+        assert_eq!(join_scopes(&BTreeSet::new()), None);
+
+        let scopes = split_scopes(Some("profile openid"));
+        assert_eq!(join_scopes(&scopes).as_deref(), Some("openid profile"));
+        assert_eq!(split_scopes(join_scopes(&scopes).as_deref()), scopes);
+    }
+}
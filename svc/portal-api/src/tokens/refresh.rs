@@ -0,0 +1,255 @@
+//! Opaque refresh tokens that are rotated on every use.
+//!
+//! Redeeming a refresh token marks it as used and issues a new one in the
+//! same family. A token is only ever redeemed once, so if a used token shows
+//! up again, either the client or an attacker holds a copy of it. Since it
+//! isn't possible to tell which, the whole family is revoked and the user has
+//! to log in again.
+//!
+//! Redeeming and revoking both lock every token in the family first, in the
+//! same order, so a token being rotated can't escape a revocation of its
+//! family.
+
+use chrono::{DateTime, Duration, Utc};
+use lerpz_axum::middleware::jwt::RevocationList;
+use lerpz_model::RefreshToken;
+use lerpz_pwd::{SecretString, TokenHasher, check_token, generate_token, token_prefix};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// The prefix of refresh tokens.
+pub const PREFIX: &str = "lrpz_rt";
+
+/// How long a refresh token can be redeemed after it was issued.
+pub const LIFETIME: Duration = Duration::days(30);
+
+/// Errors that can occur when issuing or redeeming a refresh token.
+#[derive(thiserror::Error, Debug)]
+pub enum RefreshError {
+    #[error("refresh token is invalid, expired or revoked")]
+    Invalid,
+    #[error("refresh token was already used, family {0} has been revoked")]
+    Reused(Uuid),
+    #[error(transparent)]
+    Token(#[from] lerpz_pwd::Error),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Issues a new refresh token.
+///
+/// The token joins the family `family_id`, replacing `parent` if given. Only
+/// the keyed hash of the token is stored.
+pub async fn issue(
+    tx: &mut Transaction<'_, Postgres>,
+    hasher: &TokenHasher,
+    user_id: Uuid,
+    client_id: Uuid,
+    family_id: Uuid,
+    scope: Option<&str>,
+    parent: Option<&RefreshToken>,
+) -> Result<SecretString, RefreshError> {
+    let token = generate_token(PREFIX)?;
+
+    sqlx::query(
+        "INSERT INTO refresh_tokens(token, user_id, client_id, family_id, parent_id, scope, expires_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(hasher.hash(token.expose_secret()))
+    .bind(user_id)
+    .bind(client_id)
+    .bind(family_id)
+    .bind(parent.map(|parent| parent.id))
+    .bind(scope)
+    .bind(Utc::now() + LIFETIME)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(token)
+}
+
+/// Returns the stored refresh token, whether or not it can still be redeemed.
+pub async fn find(
+    executor: impl PgExecutor<'_>,
+    hasher: &TokenHasher,
+    token: &str,
+) -> sqlx::Result<Option<RefreshToken>> {
+    if !is_refresh_token(token) {
+        return Ok(None);
+    }
+
+    sqlx::query_as("SELECT * FROM refresh_tokens WHERE token = $1")
+        .bind(hasher.hash(token))
+        .fetch_optional(executor)
+        .await
+}
+
+/// Redeems a refresh token, marking it as used.
+///
+/// Returns the stored token along with the transaction it was locked in. The
+/// replacement should be issued in the same transaction before committing it.
+///
+/// If the token was already used, its family and the access tokens issued
+/// from it are revoked before [`RefreshError::Reused`] is returned.
+pub async fn redeem(
+    database: &PgPool,
+    revocations: &RevocationList,
+    hasher: &TokenHasher,
+    token: &str,
+) -> Result<(Transaction<'static, Postgres>, RefreshToken), RefreshError> {
+    if !is_refresh_token(token) {
+        return Err(RefreshError::Invalid);
+    }
+    let hash = hasher.hash(token);
+
+    let mut tx = database.begin().await?;
+    let family_id: Uuid =
+        sqlx::query_scalar("SELECT family_id FROM refresh_tokens WHERE token = $1")
+            .bind(&hash)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(RefreshError::Invalid)?;
+    lock_family(&mut tx, family_id).await?;
+
+    let stored: RefreshToken = sqlx::query_as("SELECT * FROM refresh_tokens WHERE token = $1")
+        .bind(&hash)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if stored.used_at.is_some() {
+        tx.rollback().await?;
+        let (revoked, revoked_access) =
+            revoke_grant(database, revocations, stored.family_id).await?;
+        tracing::warn!(
+            user_id = %stored.user_id,
+            family_id = %stored.family_id,
+            revoked,
+            revoked_access,
+            "refresh token was reused, revoked its family"
+        );
+        return Err(RefreshError::Reused(stored.family_id));
+    }
+    if stored.revoked_at.is_some() || stored.expires_at <= Utc::now() {
+        return Err(RefreshError::Invalid);
+    }
+
+    sqlx::query("UPDATE refresh_tokens SET used_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(stored.id)
+        .execute(&mut *tx)
+        .await?;
+
+    Ok((tx, stored))
+}
+
+/// Revokes every token in a family, and the access tokens issued from it.
+///
+/// Returns the amount of refresh tokens and access tokens that were revoked.
+pub async fn revoke_grant(
+    database: &PgPool,
+    revocations: &RevocationList,
+    family_id: Uuid,
+) -> Result<(u64, usize), RefreshError> {
+    let mut tx = database.begin().await?;
+    let revoked = revoke_family(&mut tx, family_id).await?;
+    let revoked_access: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+        "UPDATE access_tokens SET revoked_at = CURRENT_TIMESTAMP \
+         WHERE family_id = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP \
+         RETURNING jti, expires_at",
+    )
+    .bind(family_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    for (jti, expires_at) in &revoked_access {
+        revocations.mark_revoked(jti, *expires_at).await;
+    }
+    Ok((revoked, revoked_access.len()))
+}
+
+/// Revokes every refresh token the user has for the client, and the access
+/// tokens issued from them.
+///
+/// Returns the amount of refresh tokens and access tokens that were revoked.
+pub async fn revoke_client(
+    database: &PgPool,
+    revocations: &RevocationList,
+    user_id: Uuid,
+    client_id: Uuid,
+) -> Result<(u64, usize), RefreshError> {
+    let families: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT DISTINCT family_id FROM refresh_tokens \
+         WHERE user_id = $1 AND client_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(client_id)
+    .fetch_all(database)
+    .await?;
+
+    let mut revoked = (0, 0);
+    for family_id in families {
+        let (refresh, access) = revoke_grant(database, revocations, family_id).await?;
+        revoked.0 += refresh;
+        revoked.1 += access;
+    }
+    Ok(revoked)
+}
+
+/// Revokes every token in a family that isn't revoked yet.
+///
+/// Returns the amount of tokens that were revoked.
+pub async fn revoke_family(
+    tx: &mut Transaction<'_, Postgres>,
+    family_id: Uuid,
+) -> sqlx::Result<u64> {
+    lock_family(tx, family_id).await?;
+
+    let result = sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP \
+         WHERE family_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(family_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Locks every token in a family until the transaction ends.
+///
+/// Waits for any rotation in the family to finish, so that the replacement it
+/// issued can be seen once this returns.
+async fn lock_family(tx: &mut Transaction<'_, Postgres>, family_id: Uuid) -> sqlx::Result<()> {
+    sqlx::query("SELECT id FROM refresh_tokens WHERE family_id = $1 ORDER BY id FOR UPDATE")
+        .bind(family_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Whether the token looks like a refresh token, before it is looked up.
+fn is_refresh_token(token: &str) -> bool {
+    check_token(token) && token_prefix(token) == Some(PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_refresh_token() {
+        let token = generate_token(PREFIX).unwrap();
+        assert!(is_refresh_token(token.expose_secret()));
+
+        let other = generate_token("lrpz_dc").unwrap();
+        assert!(!is_refresh_token(other.expose_secret()));
+
+        let mut tampered = token.expose_secret().to_string();
+        let last = tampered.pop().unwrap();
+        tampered.push(if last == 'A' { 'B' } else { 'A' });
+        assert!(!is_refresh_token(&tampered));
+
+        assert!(!is_refresh_token(""));
+        assert!(!is_refresh_token("lrpz_rt_"));
+    }
+}
@@ -0,0 +1,20 @@
+use axum::{Json, extract::State, http::header::CACHE_CONTROL, response::IntoResponse};
+use lerpz_jwt::KeyRing;
+
+/// How long clients may cache the key set in seconds.
+///
+/// A new key must be in the key ring for at least this long before it's made
+/// active, so that every client has seen it before tokens signed with it show
+/// up.
+pub const MAX_AGE: u32 = 3600;
+
+/// Returns the public keys that tokens from this issuer are signed with.
+///
+/// This includes retired keys until their deadline has passed, so tokens
+/// signed before a rotation keep working.
+pub async fn handler(State(keys): State<KeyRing>) -> impl IntoResponse {
+    (
+        [(CACHE_CONTROL, format!("public, max-age={MAX_AGE}"))],
+        Json(keys.jwks()),
+    )
+}
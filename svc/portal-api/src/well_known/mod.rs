@@ -0,0 +1,15 @@
+//! Documents that let other services discover and trust portal-api.
+
+use crate::state::AppState;
+
+use axum::{Router, routing::get};
+
+mod jwks;
+mod openid_configuration;
+
+pub fn router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/jwks.json", get(jwks::handler))
+        .route("/openid-configuration", get(openid_configuration::handler))
+        .with_state(state)
+}
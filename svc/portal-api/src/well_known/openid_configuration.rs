@@ -0,0 +1,91 @@
+use std::collections::BTreeSet;
+
+use axum::{Json, extract::State, http::header::CACHE_CONTROL, response::IntoResponse};
+use lerpz_jwt::KeyRing;
+use serde::Serialize;
+
+use crate::config::{self, CONFIG};
+
+/// How long clients may cache the discovery document in seconds.
+const MAX_AGE: u32 = 86400;
+
+/// The OpenID Connect discovery document.
+///
+/// See [OpenID Connect Discovery 1.0, section 3](https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata).
+#[derive(Serialize, Debug)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub device_authorization_endpoint: String,
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub scopes_supported: Vec<&'static str>,
+    pub claims_supported: Vec<&'static str>,
+    pub response_types_supported: Vec<&'static str>,
+    pub subject_types_supported: Vec<&'static str>,
+    pub id_token_signing_alg_values_supported: BTreeSet<String>,
+    pub grant_types_supported: Vec<&'static str>,
+    pub code_challenge_methods_supported: Vec<&'static str>,
+    pub token_endpoint_auth_methods_supported: Vec<&'static str>,
+}
+
+/// Returns the metadata of this issuer.
+///
+/// The authorization endpoint is the portal page at `AUTHORIZATION_URI`,
+/// since the user has to log in to the portal before it calls
+/// `/oauth/authorize` on their behalf.
+pub async fn handler(State(keys): State<KeyRing>) -> impl IntoResponse {
+    let issuer = config::issuer();
+    let configuration = OpenIdConfiguration {
+        issuer: issuer.to_string(),
+        authorization_endpoint: CONFIG.AUTHORIZATION_URI.clone(),
+        token_endpoint: format!("{issuer}/oauth/token"),
+        device_authorization_endpoint: format!("{issuer}/oauth/device_authorization"),
+        introspection_endpoint: format!("{issuer}/oauth/introspect"),
+        revocation_endpoint: format!("{issuer}/oauth/revoke"),
+        userinfo_endpoint: format!("{issuer}/oauth/userinfo"),
+        jwks_uri: format!("{issuer}/.well-known/jwks.json"),
+        scopes_supported: vec!["openid", "profile", "email"],
+        claims_supported: vec![
+            "sub",
+            "iss",
+            "aud",
+            "exp",
+            "iat",
+            "auth_time",
+            "nonce",
+            "amr",
+            "at_hash",
+            "preferred_username",
+            "picture",
+            "updated_at",
+            "email",
+        ],
+        response_types_supported: vec!["code"],
+        subject_types_supported: vec!["public"],
+        id_token_signing_alg_values_supported: keys
+            .keys()
+            .map(|key| format!("{:?}", key.algorithm()))
+            .collect(),
+        grant_types_supported: vec![
+            "authorization_code",
+            "refresh_token",
+            "client_credentials",
+            "urn:ietf:params:oauth:grant-type:device_code",
+        ],
+        code_challenge_methods_supported: vec!["S256"],
+        token_endpoint_auth_methods_supported: vec![
+            "none",
+            "client_secret_basic",
+            "client_secret_post",
+        ],
+    };
+
+    (
+        [(CACHE_CONTROL, format!("public, max-age={MAX_AGE}"))],
+        Json(configuration),
+    )
+}